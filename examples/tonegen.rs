@@ -48,11 +48,7 @@ fn run() -> ALResult<()> {
 
     try_rethrow!(listener.set_thread_context());
 
-    let name = if try_rethrow!(device.extension_present("ALC_ENUMERATE_ALL_EXT")) {
-        try_rethrow!(device.get_string(ALC_ALL_DEVICES_SPECIFIER))
-    } else {
-        try_rethrow!(device.get_string(ALC_DEVICE_SPECIFIER))
-    };
+    let name = try_rethrow!(device.name());
 
     println!("Running {}", name);
 
@@ -80,13 +76,29 @@ fn run() -> ALResult<()> {
 
     graph.audio_requested(sample.as_mut_slice(), device_srate as f64);
 
-    let buffer = try_rethrow!(ALBuffer::from_elements(&sample, ALFormat::common_stereo32f(device_srate as ALSampleRate)));
+    let buffer = try_rethrow!(ALBuffer::from_frames(&sample, device_srate as ALSampleRate));
 
     try_rethrow!(source.set_looping(false));
-    try_rethrow!(source.queue_buffers([buffer].iter().cloned()));
+    try_rethrow!(source.queue_buffers([buffer.clone()].iter().cloned()));
     try_rethrow!(source.play());
 
-    sleep(Duration::from_secs(10));
+    sleep(Duration::from_secs(5));
+
+    // Add an octave to the last few seconds while they're still to come, without touching the rest.
+    graph.add_input(DspNode::Oscillator(0.0, A5_HZ * 2.0, 0.1), synth);
+
+    let tail = device_srate as usize * 7;
+
+    graph.audio_requested(&mut sample[tail..], device_srate as f64);
+
+    // AL_SOFT_buffer_sub_data was removed in OpenAL Soft 1.18, so newer versions just play the original
+    if listener.capabilities().buffer_sub_data {
+        try_rethrow!(buffer.update_range(tail, &sample[tail..]));
+    } else {
+        println!("Updating playing buffers is not supported");
+    }
+
+    sleep(Duration::from_secs(5));
 
     try_rethrow!(source.stop());
 
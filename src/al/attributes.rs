@@ -0,0 +1,167 @@
+use als::all::*;
+
+use super::error::*;
+use super::device::*;
+use super::hrtf::*;
+
+/// Attributes used when creating a context
+///
+/// Every attribute left as `None` is chosen by the device.
+/// When returned from `ALDevice::attributes`, these are the values the device actually granted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ALContextAttributes {
+    /// Output frequency in Hz
+    pub frequency: Option<ALint>,
+    /// Mixer updates per second
+    pub refresh: Option<ALint>,
+    /// Hint for the number of mono sources
+    pub mono_sources: Option<ALint>,
+    /// Hint for the number of stereo sources
+    pub stereo_sources: Option<ALint>,
+    /// Maximum auxiliary sends per source, requires `ALC_EXT_EFX`
+    pub max_auxiliary_sends: Option<ALint>,
+    /// Enable or disable HRTF, requires `ALC_SOFT_HRTF`
+    pub hrtf: Option<bool>,
+    /// HRTF dataset to use, which also enables HRTF unless `hrtf` is `Some(false)`
+    pub hrtf_selection: Option<HrtfSelection>,
+}
+
+impl ALContextAttributes {
+    #[inline]
+    pub fn new() -> ALContextAttributes {
+        ALContextAttributes::default()
+    }
+
+    pub fn frequency(mut self, frequency: ALint) -> ALContextAttributes {
+        self.frequency = Some(frequency);
+        self
+    }
+
+    pub fn refresh(mut self, refresh: ALint) -> ALContextAttributes {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    pub fn mono_sources(mut self, count: ALint) -> ALContextAttributes {
+        self.mono_sources = Some(count);
+        self
+    }
+
+    pub fn stereo_sources(mut self, count: ALint) -> ALContextAttributes {
+        self.stereo_sources = Some(count);
+        self
+    }
+
+    pub fn max_auxiliary_sends(mut self, count: ALint) -> ALContextAttributes {
+        self.max_auxiliary_sends = Some(count);
+        self
+    }
+
+    pub fn hrtf(mut self, enabled: bool) -> ALContextAttributes {
+        self.hrtf = Some(enabled);
+        self
+    }
+
+    pub fn hrtf_selection(mut self, selection: HrtfSelection) -> ALContextAttributes {
+        self.hrtf_selection = Some(selection);
+        self
+    }
+
+    /// Select an HRTF dataset by its index in `ALDevice::hrtfs`
+    #[inline]
+    pub fn hrtf_index(self, index: ALint) -> ALContextAttributes {
+        self.hrtf_selection(HrtfSelection::Index(index))
+    }
+
+    /// Select an HRTF dataset by its name in `ALDevice::hrtfs`
+    #[inline]
+    pub fn hrtf_name(self, name: &str) -> ALContextAttributes {
+        self.hrtf_selection(HrtfSelection::Name(name.to_owned()))
+    }
+
+    /// Encode the attributes as a zero-terminated ALC attribute list for the given device.
+    ///
+    /// Returns `ALError::Unsupported` if an attribute requires an extension the device doesn't have.
+    pub fn encode(&self, device: &ALDevice) -> ALResult<Vec<ALCint>> {
+        let mut attrs = Vec::new();
+
+        if let Some(format) = device.loopback_format() {
+            // Loopback devices render at whatever frequency their format says
+            if let Some(frequency) = self.frequency {
+                if frequency != format.sample_rate as ALint {
+                    throw!(ALError::InvalidValue);
+                }
+            }
+
+            attrs.extend_from_slice(&[ALC_FORMAT_CHANNELS_SOFT, format.channels(),
+                                      ALC_FORMAT_TYPE_SOFT, format.sample_type(),
+                                      ALC_FREQUENCY, format.sample_rate as ALCint]);
+        } else if let Some(frequency) = self.frequency {
+            attrs.extend_from_slice(&[ALC_FREQUENCY, frequency]);
+        }
+
+        if let Some(refresh) = self.refresh {
+            attrs.extend_from_slice(&[ALC_REFRESH, refresh]);
+        }
+
+        if let Some(count) = self.mono_sources {
+            attrs.extend_from_slice(&[ALC_MONO_SOURCES, count]);
+        }
+
+        if let Some(count) = self.stereo_sources {
+            attrs.extend_from_slice(&[ALC_STEREO_SOURCES, count]);
+        }
+
+        if let Some(count) = self.max_auxiliary_sends {
+            if !device.capabilities().efx {
+                throw!(ALError::Unsupported);
+            }
+
+            attrs.extend_from_slice(&[ALC_MAX_AUXILIARY_SENDS, count]);
+        }
+
+        if self.hrtf.is_some() || self.hrtf_selection.is_some() {
+            if !device.capabilities().hrtf {
+                throw!(ALError::Unsupported);
+            }
+
+            let enabled = self.hrtf.unwrap_or(true);
+
+            attrs.extend_from_slice(&[ALC_HRTF_SOFT, if enabled { ALC_TRUE } else { ALC_FALSE } as ALCint]);
+
+            if let Some(ref selection) = self.hrtf_selection {
+                attrs.extend_from_slice(&[ALC_HRTF_ID_SOFT, try_rethrow!(selection.resolve(device))]);
+            }
+        }
+
+        attrs.push(0);
+
+        Ok(attrs)
+    }
+
+    /// Decode a zero-terminated ALC attribute list, as returned by `ALC_ALL_ATTRIBUTES`.
+    ///
+    /// Unknown attributes are ignored.
+    pub fn decode(attrs: &[ALCint]) -> ALResult<ALContextAttributes> {
+        let mut res = ALContextAttributes::new();
+
+        for pair in attrs.chunks(2) {
+            if pair.len() < 2 || pair[0] == 0 { break; }
+
+            let value = pair[1];
+
+            match pair[0] {
+                ALC_FREQUENCY => res.frequency = Some(value),
+                ALC_REFRESH => res.refresh = Some(value),
+                ALC_MONO_SOURCES => res.mono_sources = Some(value),
+                ALC_STEREO_SOURCES => res.stereo_sources = Some(value),
+                ALC_MAX_AUXILIARY_SENDS => res.max_auxiliary_sends = Some(value),
+                ALC_HRTF_SOFT => res.hrtf = Some(value == ALC_TRUE as ALCint),
+                ALC_HRTF_ID_SOFT => res.hrtf_selection = Some(HrtfSelection::Index(value)),
+                _ => {}
+            }
+        }
+
+        Ok(res)
+    }
+}
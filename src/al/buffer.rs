@@ -1,19 +1,33 @@
 use als::all::*;
 
+use std::fmt;
 use std::mem;
-use std::sync::Arc;
+use std::time::Duration;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::os::raw::c_void;
-use std::cell::Cell;
 
 use super::error::*;
 use super::format::*;
+use super::sample::*;
+use super::capabilities::*;
 
 use super::ALObject;
 
-#[derive(PartialEq, Eq)]
-pub struct ALBuffer(ALuint, Cell<Option<ALFormat>>, Cell<usize>);
+// What is known about the buffered data, which must always be read and changed as a whole
+struct BufferState {
+    format: Option<ALFormat>,
+    num_bytes: usize,
+    // Custom loop points are kept on this side, so re-uploading data doesn't have to query them
+    loop_points: Option<(usize, usize)>,
+}
+
+pub struct ALBuffer {
+    raw: ALuint,
+    // Held across every call that changes the buffer data or depends on it
+    state: Mutex<BufferState>,
+}
 
-impl_simple_alobject!(simple ALBuffer, alIsBuffer);
+impl_simple_alobject!(struct ALBuffer, alIsBuffer);
 
 impl ALBuffer {
     pub fn new() -> ALResult<Arc<ALBuffer>> {
@@ -21,12 +35,15 @@ impl ALBuffer {
 
         unsafe { alGenBuffers(1, &mut buffer as *mut _); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGenBuffers);
 
-        Ok(Arc::new(ALBuffer(buffer, Cell::new(None), Cell::new(0))))
+        Ok(Arc::new(ALBuffer {
+            raw: buffer,
+            state: Mutex::new(BufferState { format: None, num_bytes: 0, loop_points: None }),
+        }))
     }
 
-    pub fn from_elements<T>(data: &Vec<T>, format: ALFormat) -> ALResult<Arc<ALBuffer>> {
+    pub fn from_elements<F: ALFrame>(data: &Vec<F>, format: ALFormat) -> ALResult<Arc<ALBuffer>> {
         let buffer = try_rethrow!(ALBuffer::new());
 
         try_rethrow!(buffer.buffer_elements(data, format));
@@ -34,7 +51,7 @@ impl ALBuffer {
         Ok(buffer)
     }
 
-    pub fn from_slice<T>(data: &[T], format: ALFormat) -> ALResult<Arc<ALBuffer>> {
+    pub fn from_slice<F: ALFrame>(data: &[F], format: ALFormat) -> ALResult<Arc<ALBuffer>> {
         let buffer = try_rethrow!(ALBuffer::new());
 
         try_rethrow!(buffer.buffer_slice(data, format));
@@ -42,6 +59,15 @@ impl ALBuffer {
         Ok(buffer)
     }
 
+    /// Create a buffer from frames, with the format derived from the frame type
+    pub fn from_frames<F: ALFrame>(data: &[F], sample_rate: ALSampleRate) -> ALResult<Arc<ALBuffer>> {
+        let buffer = try_rethrow!(ALBuffer::new());
+
+        try_rethrow!(buffer.buffer_frames(data, sample_rate));
+
+        Ok(buffer)
+    }
+
     pub unsafe fn from_raw(data: *const c_void, size: usize, samples: usize, format: ALFormat) -> ALResult<Arc<ALBuffer>> {
         let buffer = try_rethrow!(ALBuffer::new());
 
@@ -50,49 +76,297 @@ impl ALBuffer {
         Ok(buffer)
     }
 
+    fn state(&self) -> MutexGuard<BufferState> {
+        // Every field is updated before anything can panic, so a poisoned lock still holds a consistent state
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
     /// Returns the last number of bytes buffered
-    #[inline(always)]
-    pub fn num_bytes(&self) -> usize { self.2.get() }
+    #[inline]
+    pub fn num_bytes(&self) -> usize { self.state().num_bytes }
+
+    /// Returns the format of the last data buffered, if any
+    #[inline]
+    pub fn format(&self) -> Option<ALFormat> { self.state().format }
+
+    fn get_integer(&self, param: ALenum) -> ALResult<ALint> {
+        try_rethrow!(self.check());
+
+        let mut value = 0;
+
+        unsafe { alGetBufferi(self.raw, param, &mut value); }
+
+        check_al_errors!(fn alGetBufferi, object self.raw, param param);
+
+        Ok(value)
+    }
+
+    /// Sample rate of the buffer data in Hz
+    pub fn frequency(&self) -> ALResult<ALSampleRate> {
+        Ok(try_rethrow!(self.get_integer(AL_FREQUENCY)) as ALSampleRate)
+    }
+
+    /// Bits per sample of a single channel, as stored by OpenAL
+    pub fn bits(&self) -> ALResult<usize> {
+        Ok(try_rethrow!(self.get_integer(AL_BITS)) as usize)
+    }
+
+    /// Number of channels in a single frame
+    pub fn channels(&self) -> ALResult<usize> {
+        Ok(try_rethrow!(self.get_integer(AL_CHANNELS)) as usize)
+    }
+
+    /// Size of the buffer data in bytes, as stored by OpenAL
+    ///
+    /// This can differ from `num_bytes` if OpenAL converted the data to another storage type.
+    pub fn size(&self) -> ALResult<usize> {
+        Ok(try_rethrow!(self.get_integer(AL_SIZE)) as usize)
+    }
+
+    /// Length of the buffer data in sample frames
+    pub fn frames(&self) -> ALResult<usize> {
+        let frame_bits = try_rethrow!(self.channels()) * try_rethrow!(self.bits());
+
+        if frame_bits == 0 {
+            Ok(0)
+        } else {
+            Ok(try_rethrow!(self.size()) * 8 / frame_bits)
+        }
+    }
+
+    /// Playback length of the buffer data at its own sample rate
+    pub fn duration(&self) -> ALResult<Duration> {
+        let frames = try_rethrow!(self.frames()) as u64;
+        let frequency = try_rethrow!(self.frequency()) as u64;
+
+        if frequency == 0 {
+            Ok(Duration::new(0, 0))
+        } else {
+            Ok(Duration::new(frames / frequency, ((frames % frequency) * 1_000_000_000 / frequency) as u32))
+        }
+    }
 
-    /// Buffer a `Vec<T>` of elements `T` to the `ALBuffer`
+    /// Buffer a `Vec<F>` of frames `F` to the `ALBuffer`
     #[inline]
-    pub fn buffer_elements<T>(&self, data: &Vec<T>, format: ALFormat) -> ALResult<()> {
-        unsafe { self.buffer_raw(data.as_ptr() as *const c_void, data.len() * mem::size_of::<T>(), data.len(), format) }
+    pub fn buffer_elements<F: ALFrame>(&self, data: &Vec<F>, format: ALFormat) -> ALResult<()> {
+        self.buffer_slice(data.as_slice(), format)
     }
 
-    /// Buffer a slice of `T` to the `ALBuffer`
+    /// Buffer a slice of frames `F` to the `ALBuffer`
+    ///
+    /// Returns `ALError::InvalidValue` if the frames don't match the channels and sample type of `format`.
+    pub fn buffer_slice<F: ALFrame>(&self, data: &[F], format: ALFormat) -> ALResult<()> {
+        if !F::matches(&format) {
+            throw!(ALError::InvalidValue);
+        }
+
+        unsafe { self.buffer_raw(data.as_ptr() as *const c_void, data.len() * mem::size_of::<F>(), data.len(), format) }
+    }
+
+    /// Buffer a slice of frames `F` to the `ALBuffer`, with the format derived from the frame type
     #[inline]
-    pub fn buffer_slice<T>(&self, data: &[T], format: ALFormat) -> ALResult<()> {
-        unsafe { self.buffer_raw(data.as_ptr() as *const c_void, data.len() * mem::size_of::<T>(), data.len(), format) }
+    pub fn buffer_frames<F: ALFrame>(&self, data: &[F], sample_rate: ALSampleRate) -> ALResult<()> {
+        self.buffer_slice(data, F::format(sample_rate))
     }
 
     /// Buffer raw data to the `ALBuffer`
+    ///
+    /// `samples` is the number of sample frames in `data`.
+    /// Uses `AL_SOFT_buffer_samples` if available, otherwise `alBufferData`, see `ALFormat::is_supported`.
     pub unsafe fn buffer_raw(&self, data: *const c_void, size: usize, samples: usize, format: ALFormat) -> ALResult<()> {
         if data.is_null() || size == 0 {
             throw!(ALError::InvalidValue);
         } else {
             try_rethrow!(self.check());
 
-            let internal_format = format.internal_format();
-            let channels = format.channels();
-            let sample_type = format.sample_type();
+            try_rethrow!(format.validate());
+
+            if !try_rethrow!(format.is_supported()) {
+                throw!(ALError::Unsupported);
+            }
+
+            // Hold the state across the upload, so nobody sees the new data with the old format or size
+            let mut state = self.state();
+
+            let same_format = state.format == Some(format);
+
+            if try_rethrow!(ALCapabilities::current()).buffer_samples {
+                let internal_format = format.internal_format();
+                let channels = format.channels();
+                let sample_type = format.sample_type();
+
+                alBufferSamplesSOFT(self.raw, format.sample_rate as ALuint, internal_format, samples as ALsizei, channels, sample_type, data);
+
+                check_al_errors!(fn alBufferSamplesSOFT, object self.raw);
+            } else {
+                // is_supported already made sure there is one
+                let buffer_format = match try_rethrow!(format.buffer_data_format()) {
+                    Some(buffer_format) => buffer_format,
+                    None => throw!(ALError::Unsupported),
+                };
+
+                alBufferData(self.raw, buffer_format, data, size as ALsizei, format.sample_rate as ALsizei);
+
+                check_al_errors!(fn alBufferData, object self.raw);
+            }
 
-            alBufferSamplesSOFT(self.0, format.sample_rate as ALuint, internal_format, samples as ALsizei, channels, sample_type, data);
+            state.format = Some(format);
+            state.num_bytes = size;
 
-            check_al_errors!();
+            let loop_points = state.loop_points;
 
-            self.1.set(Some(format));
-            self.2.set(size);
+            // New data resets the loop points, so restore custom ones if the format stayed the same and they still fit
+            match loop_points {
+                Some((start, end)) if same_format && end <= samples => self.apply_loop_points(start, end),
+                Some(_) => {
+                    state.loop_points = None;
 
-            Ok(())
+                    Ok(())
+                },
+                None => Ok(()),
+            }
         }
     }
+
+    /// Overwrite buffer data starting at sample frame `frame_offset`, even while the buffer is playing.
+    ///
+    /// The frames must have the channels and sample type the buffer data was last buffered with.
+    /// Returns `ALError::InvalidValue` if they don't, or if the range extends past the end of the buffer.
+    ///
+    /// Requires `AL_SOFT_buffer_sub_data`, which only accepts data buffered as
+    /// `u8`, `i16` or `f32` samples, so other sample types return `ALError::Unsupported`.
+    /// OpenAL Soft removed the extension in 1.18, so this returns `ALError::Unsupported` on any newer version.
+    pub fn update_range<F: ALFrame>(&self, frame_offset: usize, data: &[F]) -> ALResult<()> {
+        try_rethrow!(self.check());
+
+        if !try_rethrow!(ALCapabilities::current()).buffer_sub_data {
+            throw!(ALError::Unsupported);
+        }
+
+        let state = self.state();
+
+        let format = match state.format {
+            Some(format) => format,
+            None => throw!(ALError::InvalidOperation),
+        };
+
+        // The update is named by a format enum, which only exists for these sample types
+        match format.sample_type {
+            ALSampleType::UnsignedByte | ALSampleType::Short | ALSampleType::Float => {},
+            _ => throw!(ALError::Unsupported),
+        }
+
+        if data.is_empty() || !F::matches(&format) {
+            throw!(ALError::InvalidValue);
+        }
+
+        match frame_offset.checked_add(data.len()) {
+            Some(end) if end <= format.frames_for(state.num_bytes) => {},
+            _ => throw!(ALError::InvalidValue),
+        }
+
+        let frame_size = format.frame_size();
+
+        unsafe {
+            alBufferSubDataSOFT(self.raw, format.internal_format(), data.as_ptr() as *const c_void,
+                                (frame_offset * frame_size) as ALsizei, (data.len() * frame_size) as ALsizei);
+        }
+
+        check_al_errors!(fn alBufferSubDataSOFT, object self.raw);
+
+        Ok(())
+    }
+
+    /// Loop only the sample frames from `start` up to `end` when a looping source plays this buffer.
+    ///
+    /// Playback still begins at the start of the buffer, so anything before `start` acts as an intro.
+    /// Returns `ALError::InvalidValue` unless `start < end` and `end` is within the buffered frames.
+    ///
+    /// The points are kept when data in the same format and long enough is buffered again.
+    ///
+    /// Requires `AL_SOFT_loop_points`, and the buffer can't be attached to any source.
+    pub fn set_loop_points(&self, start: usize, end: usize) -> ALResult<()> {
+        try_rethrow!(self.check());
+
+        if !try_rethrow!(ALCapabilities::current()).loop_points {
+            throw!(ALError::Unsupported);
+        }
+
+        let mut state = self.state();
+
+        let num_bytes = state.num_bytes;
+        let frames = state.format.map_or(0, |format| format.frames_for(num_bytes));
+
+        if start >= end || end > frames {
+            throw!(ALError::InvalidValue);
+        }
+
+        try_rethrow!(self.apply_loop_points(start, end));
+
+        // Looping the whole buffer is the default, which new data resets to anyway
+        state.loop_points = if start == 0 && end == frames { None } else { Some((start, end)) };
+
+        Ok(())
+    }
+
+    /// Returns the looped section as start and end sample frames, which is the whole buffer by default
+    pub fn loop_points(&self) -> ALResult<(usize, usize)> {
+        try_rethrow!(self.check());
+
+        if !try_rethrow!(ALCapabilities::current()).loop_points {
+            throw!(ALError::Unsupported);
+        }
+
+        // Don't read the points while an upload is between resetting and restoring them
+        let _state = self.state();
+
+        let mut points: [ALint; 2] = [0; 2];
+
+        unsafe { alGetBufferiv(self.raw, AL_LOOP_POINTS_SOFT, points.as_mut_ptr()); }
+
+        check_al_errors!(fn alGetBufferiv, object self.raw, param AL_LOOP_POINTS_SOFT);
+
+        Ok((points[0] as usize, points[1] as usize))
+    }
+
+    fn apply_loop_points(&self, start: usize, end: usize) -> ALResult<()> {
+        let points = [start as ALint, end as ALint];
+
+        unsafe { alBufferiv(self.raw, AL_LOOP_POINTS_SOFT, points.as_ptr()); }
+
+        check_al_errors!(fn alBufferiv, object self.raw, param AL_LOOP_POINTS_SOFT);
+
+        Ok(())
+    }
 }
 
+impl fmt::Debug for ALBuffer {
+    // Only shows what is known without calling into OpenAL, which needs a current context
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state();
+
+        f.debug_struct("ALBuffer")
+         .field("id", &self.raw)
+         .field("format", &state.format)
+         .field("num_bytes", &state.num_bytes)
+         .finish()
+    }
+}
+
+impl PartialEq for ALBuffer {
+    #[inline]
+    fn eq(&self, other: &ALBuffer) -> bool { self.raw == other.raw }
+}
+
+impl Eq for ALBuffer {}
+
 impl Drop for ALBuffer {
     fn drop(&mut self) {
-        unsafe { alDeleteBuffers(1, &self.0); }
+        unsafe { alDeleteBuffers(1, &self.raw); }
 
-        ALError::check().unwrap();
+        ALError::report_drop_error(ALError::check());
     }
 }
\ No newline at end of file
@@ -0,0 +1,153 @@
+//! Extension registry, queried once per device and context
+
+use als::all::*;
+
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::sync::atomic::{Ordering, AtomicUsize, ATOMIC_USIZE_INIT};
+
+use super::error::*;
+use super::state::*;
+use super::device::*;
+
+lazy_static! {
+    // Capabilities of every live context by its raw pointer, for objects that aren't owned by a context
+    static ref CONTEXT_CAPABILITIES: Mutex<Vec<(usize, ALCapabilities)>> = Mutex::new(Vec::new());
+}
+
+// Changed whenever a context is destroyed, as a new context may reuse its pointer
+static CONTEXT_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+thread_local! {
+    // Last context looked up on this thread, with the generation it was looked up in
+    static CURRENT_CAPABILITIES: Cell<Option<(usize, usize, ALCapabilities)>> = Cell::new(None);
+}
+
+macro_rules! capabilities {
+    {
+        alc { $( $(#[$alc_attr:meta])* $alc_field:ident: $alc_ext:expr, )* }
+        al { $( $(#[$al_attr:meta])* $al_field:ident: $al_ext:expr, )* }
+    } => {
+        /// Extensions wrapped by this crate that are supported by a device or context
+        ///
+        /// ALC extensions are filled in for every device, while AL extensions
+        /// are only filled in for contexts and are always `false` on a device.
+        #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+        pub struct ALCapabilities {
+            $(
+                $(#[$alc_attr])*
+                pub $alc_field: bool,
+            )*
+            $(
+                $(#[$al_attr])*
+                pub $al_field: bool,
+            )*
+        }
+
+        impl ALCapabilities {
+            /// Query ALC extensions of a device
+            pub fn query_device(device: &ALDevice) -> ALResult<ALCapabilities> {
+                Ok(ALCapabilities {
+                    $(
+                        $alc_field: try_rethrow!(device.extension_present($alc_ext)),
+                    )*
+                    $(
+                        $al_field: false,
+                    )*
+                })
+            }
+
+            /// Query AL extensions of the current context, on top of the capabilities of its device
+            pub fn query_context(device: &ALDevice) -> ALResult<ALCapabilities> {
+                let mut capabilities = *device.capabilities();
+
+                $(
+                    capabilities.$al_field = try_rethrow!(ALState::extension_present($al_ext));
+                )*
+
+                Ok(capabilities)
+            }
+        }
+    }
+}
+
+capabilities! {
+    alc {
+        /// `ALC_ENUMERATE_ALL_EXT`
+        enumerate_all: "ALC_ENUMERATE_ALL_EXT",
+        /// `ALC_EXT_EFX`
+        efx: "ALC_EXT_EFX",
+        /// `ALC_EXT_disconnect`
+        disconnect: "ALC_EXT_disconnect",
+        /// `ALC_EXT_thread_local_context`
+        thread_local_context: "ALC_EXT_thread_local_context",
+        /// `ALC_SOFT_HRTF`
+        hrtf: "ALC_SOFT_HRTF",
+        /// `ALC_SOFT_loopback`
+        loopback: "ALC_SOFT_loopback",
+        /// `ALC_SOFT_pause_device`
+        pause_device: "ALC_SOFT_pause_device",
+    }
+    al {
+        /// `AL_SOFT_buffer_samples`
+        buffer_samples: "AL_SOFT_buffer_samples",
+        /// `AL_SOFT_buffer_sub_data`
+        buffer_sub_data: "AL_SOFT_buffer_sub_data",
+        /// `AL_SOFT_loop_points`
+        loop_points: "AL_SOFT_loop_points",
+        /// `AL_SOFT_deferred_updates`
+        deferred_updates: "AL_SOFT_deferred_updates",
+        /// `AL_EXT_source_distance_model`
+        source_distance_model: "AL_EXT_source_distance_model",
+    }
+}
+
+impl ALCapabilities {
+    /// Capabilities of the context that is current on this thread
+    ///
+    /// Returns `ALError::InvalidContext` if no context created by this crate is current.
+    pub fn current() -> ALResult<ALCapabilities> {
+        let raw = unsafe { alcGetCurrentContext() } as usize;
+        let generation = CONTEXT_GENERATION.load(Ordering::SeqCst);
+
+        // Calls mostly come from the same context as the last one, so only consult the shared registry when it changes
+        if let Some((context, cached, capabilities)) = CURRENT_CAPABILITIES.try_with(|cached| cached.get()).unwrap_or(None) {
+            if context == raw && cached == generation {
+                return Ok(capabilities);
+            }
+        }
+
+        let found = match CONTEXT_CAPABILITIES.lock() {
+            Ok(contexts) => contexts.iter().find(|&&(context, _)| context == raw).map(|&(_, capabilities)| capabilities),
+            Err(_) => None,
+        };
+
+        match found {
+            Some(capabilities) => {
+                let _ = CURRENT_CAPABILITIES.try_with(|cached| cached.set(Some((raw, generation, capabilities))));
+
+                Ok(capabilities)
+            },
+            None => throw!(ALError::InvalidContext),
+        }
+    }
+}
+
+/// Record the capabilities of a new context for `ALCapabilities::current`
+#[doc(hidden)]
+pub fn register_context(context: *mut ALCcontext, capabilities: ALCapabilities) {
+    if let Ok(mut contexts) = CONTEXT_CAPABILITIES.lock() {
+        contexts.push((context as usize, capabilities));
+    }
+}
+
+/// Forget a context before it is destroyed
+#[doc(hidden)]
+pub fn unregister_context(context: *mut ALCcontext) {
+    if let Ok(mut contexts) = CONTEXT_CAPABILITIES.lock() {
+        contexts.retain(|&(other, _)| other != context as usize);
+    }
+
+    // Entries cached by other threads may now refer to a destroyed context
+    CONTEXT_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
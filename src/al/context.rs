@@ -2,70 +2,213 @@ use als::all::*;
 
 use std::ptr;
 use std::sync::Arc;
-use std::cell::Cell;
+use std::sync::atomic::{Ordering, AtomicUsize};
 use std::ops::Deref;
 
 use super::error::*;
 use super::device::*;
+use super::attributes::*;
 use super::listener::*;
+use super::capabilities::*;
 
 pub struct ALContext {
     raw: *mut ALCcontext,
     device: Arc<ALDevice>,
-    thread_local: Cell<bool>,
+    // Number of live `DeferredUpdates` guards
+    deferred: AtomicUsize,
+    capabilities: ALCapabilities,
 }
 
+unsafe impl Sync for ALContext {}
+
+unsafe impl Send for ALContext {}
+
 impl ALContext {
     #[inline(always)]
     pub unsafe fn raw(&self) -> *mut ALCcontext { self.raw }
 
+    #[inline]
     pub fn create_from_device(device: Arc<ALDevice>) -> ALResult<Arc<ALContext>> {
-        let ctx = unsafe { alcCreateContext(device.raw(), ptr::null()) };
+        ALContext::create_with_attributes(device, &ALContextAttributes::new())
+    }
+
+    /// Create a context with the requested attributes.
+    ///
+    /// The device may not grant every request, so use `attributes` afterwards to see what was applied.
+    pub fn create_with_attributes(device: Arc<ALDevice>, attributes: &ALContextAttributes) -> ALResult<Arc<ALContext>> {
+        let attrs = try_rethrow!(attributes.encode(&device));
+
+        let ctx = unsafe { alcCreateContext(device.raw(), attrs.as_ptr()) };
 
         if ctx.is_null() {
-            check_alc_errors!();
+            // The reason is queued on the device, so don't leave it for an unrelated check
+            let _ = ALError::check_device(unsafe { device.raw() });
 
-            panic!("Could not create OpenAL context");
+            throw!(ALError::ContextCreationFailed);
         }
 
-        let ctx = Arc::new(ALContext { raw: ctx, device: device, thread_local: Cell::new(false) });
+        let mut ctx = ALContext {
+            raw: ctx,
+            // ALC extensions are already known, and decide how the AL ones can be queried
+            capabilities: *device.capabilities(),
+            device: device,
+            deferred: AtomicUsize::new(0),
+        };
 
         try_rethrow!(ctx.make_current());
 
-        Ok(ctx)
+        // AL extensions are queried through the current context, which a thread-local context would shadow
+        let capabilities = {
+            let _bound = if ctx.capabilities.thread_local_context { Some(try_rethrow!(ctx.bind())) } else { None };
+
+            try_rethrow!(ALCapabilities::query_context(&ctx.device))
+        };
+
+        ctx.capabilities = capabilities;
+
+        register_context(ctx.raw, capabilities);
+
+        Ok(Arc::new(ctx))
     }
 
     pub fn device(&self) -> Arc<ALDevice> { self.device.clone() }
 
+    /// Extensions supported by this context and its device, queried once when it was created
+    #[inline(always)]
+    pub fn capabilities(&self) -> &ALCapabilities { &self.capabilities }
+
+    /// Attribute values actually in use by the device of this context
+    #[inline]
+    pub fn attributes(&self) -> ALResult<ALContextAttributes> {
+        self.device.attributes()
+    }
+
+    /// Check if this context is the one OpenAL calls currently go to on this thread
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        unsafe { alcGetCurrentContext() == self.raw }
+    }
+
+    /// Returns `ALError::InvalidContext` if this context isn't current on this thread.
+    ///
+    /// Objects use this before every call, so they never act on an object of another context with the same name.
+    pub fn ensure_current(&self) -> ALResult<()> {
+        if self.is_current() { Ok(()) } else {
+            throw!(ALError::InvalidContext);
+        }
+    }
+
     pub fn make_current(&self) -> ALResult<()> {
         if ALC_TRUE != unsafe { alcMakeContextCurrent(self.raw) } {
-            check_alc_errors!();
+            check_alc_errors!(fn alcMakeContextCurrent);
 
-            panic!("Could not make context current");
+            throw!(ALError::MakeCurrentFailed);
         }
 
-        self.thread_local.set(false);
-
         Ok(())
     }
 
+    /// Make this context current on this thread only, overriding the process-wide current context
+    ///
+    /// Requires `ALC_EXT_thread_local_context`.
     pub fn set_thread_context(&self) -> ALResult<()> {
+        if !self.capabilities.thread_local_context {
+            throw!(ALError::Unsupported);
+        }
+
         if ALC_TRUE != unsafe { alcSetThreadContext(self.raw) } {
-            check_alc_errors!();
+            check_alc_errors!(fn alcSetThreadContext);
 
-            panic!("Could not make context current");
+            throw!(ALError::MakeCurrentFailed);
         }
 
-        self.thread_local.set(true);
-
         Ok(())
     }
 
+    /// Make this context current on this thread until the returned guard is dropped,
+    /// which restores whatever context was current on this thread before.
+    ///
+    /// Guards can be nested, and only affect the thread they were created on.
+    /// Requires `ALC_EXT_thread_local_context`.
+    ///
+    /// ```ignore
+    /// {
+    ///     let _bound = try_rethrow!(ui_context.bind());
+    ///
+    ///     try_rethrow!(ui_source.play());
+    /// }
+    /// // The game context is current again
+    /// ```
+    pub fn bind(&self) -> ALResult<CurrentContextGuard> {
+        if !self.capabilities.thread_local_context {
+            throw!(ALError::Unsupported);
+        }
+
+        let previous = unsafe { alcGetThreadContext() };
+
+        try_rethrow!(self.set_thread_context());
+
+        Ok(CurrentContextGuard { context: self, previous: previous })
+    }
+
+    /// Defer all property changes on this context until the returned guard is finished or dropped,
+    /// at which point they take effect at once.
+    ///
+    /// Uses `AL_SOFT_deferred_updates` if available, otherwise falls back to `suspend`/`process`.
+    /// Guards can be nested, in which case updates are applied when the outermost one ends.
+    ///
+    /// This context must be current. If a guard ends while this context is not current and can't be bound,
+    /// the error is returned and the updates stay deferred.
+    pub fn defer_updates(&self) -> ALResult<DeferredUpdates> {
+        try_rethrow!(self.ensure_current());
+
+        let extension = self.capabilities.deferred_updates;
+
+        if self.deferred.fetch_add(1, Ordering::SeqCst) == 0 {
+            let res = if extension {
+                unsafe { alDeferUpdatesSOFT(); }
+
+                ALError::check_call(ALCall::new("alDeferUpdatesSOFT", None, None))
+            } else {
+                self.suspend()
+            };
+
+            if let Err(err) = res {
+                self.deferred.fetch_sub(1, Ordering::SeqCst);
+
+                return Err(err);
+            }
+        }
+
+        Ok(DeferredUpdates { context: self, extension: extension, finished: false })
+    }
+
+    /// Run `f` with all property changes deferred, applying them at once afterwards
+    ///
+    /// ```ignore
+    /// try_rethrow!(context.batch(|| {
+    ///     for &(ref source, position) in &moved {
+    ///         try_rethrow!(source.set_position(position));
+    ///     }
+    ///
+    ///     Ok(())
+    /// }));
+    /// ```
+    pub fn batch<F, R>(&self, f: F) -> ALResult<R> where F: FnOnce() -> ALResult<R> {
+        let deferred = try_rethrow!(self.defer_updates());
+
+        let res = try_rethrow!(f());
+
+        try_rethrow!(deferred.finish());
+
+        Ok(res)
+    }
+
     #[inline]
     pub fn suspend(&self) -> ALResult<()> {
         unsafe { alcSuspendContext(self.raw); }
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcSuspendContext);
 
         Ok(())
     }
@@ -74,7 +217,7 @@ impl ALContext {
     pub fn process(&self) -> ALResult<()> {
         unsafe { alcProcessContext(self.raw); }
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcProcessContext);
 
         Ok(())
     }
@@ -87,6 +230,75 @@ impl Deref for ALContext {
     fn deref(&self) -> &Arc<ALDevice> { &self.device }
 }
 
+/// Defers property updates on a context while alive, see `ALContext::defer_updates`
+pub struct DeferredUpdates<'a> {
+    context: &'a ALContext,
+    extension: bool,
+    finished: bool,
+}
+
+impl<'a> DeferredUpdates<'a> {
+    /// Apply all deferred updates now, returning any error instead of discarding it on drop
+    pub fn finish(mut self) -> ALResult<()> {
+        self.finished = true;
+
+        self.process()
+    }
+
+    fn process(&self) -> ALResult<()> {
+        // alProcessUpdatesSOFT applies to the current context, which may have changed since the guard was created.
+        // Bind before giving up this guard's share of the deferral, so a failure doesn't lose track of it.
+        let _bound = if !self.extension || self.context.is_current() { None } else {
+            Some(try_rethrow!(self.context.bind()))
+        };
+
+        // Nested guards leave processing to the outermost one
+        if self.context.deferred.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return Ok(());
+        }
+
+        if self.extension {
+            unsafe { alProcessUpdatesSOFT(); }
+
+            check_al_errors!(fn alProcessUpdatesSOFT);
+
+            Ok(())
+        } else {
+            self.context.process()
+        }
+    }
+}
+
+impl<'a> Drop for DeferredUpdates<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            ALError::report_drop_error(self.process());
+        }
+    }
+}
+
+/// Keeps a context current on this thread, see `ALContext::bind`
+pub struct CurrentContextGuard<'a> {
+    context: &'a ALContext,
+    // Being a raw pointer also keeps the guard on the thread it was created on
+    previous: *mut ALCcontext,
+}
+
+impl<'a> CurrentContextGuard<'a> {
+    /// The context bound by this guard
+    #[inline(always)]
+    pub fn context(&self) -> &ALContext { self.context }
+}
+
+impl<'a> Drop for CurrentContextGuard<'a> {
+    fn drop(&mut self) {
+        // A null previous context falls back to the process-wide current context again
+        if ALC_TRUE != unsafe { alcSetThreadContext(self.previous) } {
+            ALError::report_drop_error(ALError::check_device(unsafe { self.context.device.raw() }));
+        }
+    }
+}
+
 pub trait ALContextArc {
     fn create_listener(&self) -> Arc<ALListener>;
 }
@@ -99,17 +311,22 @@ impl ALContextArc for Arc<ALContext> {
 
 impl Drop for ALContext {
     fn drop(&mut self) {
+        unregister_context(self.raw);
+
         unsafe {
-            // Stop using this context before destroying it
-            if self.thread_local.get() {
+            // Stop using this context before destroying it, without touching any other current context
+            if self.capabilities.thread_local_context && alcGetThreadContext() == self.raw {
                 alcSetThreadContext(ptr::null_mut());
-            } else {
+            }
+
+            if alcGetCurrentContext() == self.raw {
                 alcMakeContextCurrent(ptr::null_mut());
             }
 
             alcDestroyContext(self.raw);
         }
 
-        ALError::check_alc().unwrap();
+        // The context is no longer current, so check its device directly
+        ALError::report_drop_error(ALError::check_device(unsafe { self.device.raw() }));
     }
 }
\ No newline at end of file
@@ -1,25 +1,56 @@
 use als::all::*;
-use als::ext::ALC_ENUMERATE_ALL_EXT_NAME;
 
 use std::ptr;
+use std::mem;
 use std::sync::Arc;
+use std::sync::atomic::{Ordering, AtomicUsize};
+use std::os::raw::c_void;
+use std::ops::Deref;
 use std::borrow::Cow;
 use std::ffi::{CString, CStr};
 
 use super::error::*;
+use super::format::*;
+use super::sample::*;
 use super::context::*;
+use super::attributes::*;
 use super::listener::*;
+use super::capabilities::*;
 
 lazy_static! {
     /// `NULL_DEVICE` is useful for checking system capabilities before creating a real device instance.
-    pub static ref NULL_DEVICE: Arc<ALDevice> = Arc::new(ALDevice { raw: ptr::null_mut() });
+    pub static ref NULL_DEVICE: Arc<ALDevice> = Arc::new(ALDevice::from_raw(ptr::null_mut(), None).unwrap_or(ALDevice {
+        raw: ptr::null_mut(),
+        loopback: None,
+        capabilities: ALCapabilities::default(),
+        pause_depth: AtomicUsize::new(0),
+    }));
+}
+
+/// Owned description of an available device
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Name that can be given to `ALDevice::open` or `ALCaptureDevice::open`
+    pub name: String,
+    /// Whether this is the current system default device
+    pub is_default: bool,
 }
 
-#[derive(Eq, PartialEq)]
 pub struct ALDevice {
     raw: *mut ALCdevice,
+    loopback: Option<ALFormat>,
+    capabilities: ALCapabilities,
+    // Number of live `PausedDevice` guards
+    pause_depth: AtomicUsize,
 }
 
+impl PartialEq for ALDevice {
+    #[inline]
+    fn eq(&self, other: &ALDevice) -> bool { self.raw == other.raw }
+}
+
+impl Eq for ALDevice {}
+
 unsafe impl Sync for ALDevice {}
 
 unsafe impl Send for ALDevice {}
@@ -28,6 +59,22 @@ impl ALDevice {
     #[inline(always)]
     pub unsafe fn raw(&self) -> *mut ALCdevice { self.raw }
 
+    /// Take ownership of an opened device and query its capabilities.
+    ///
+    /// The device is closed again if the query fails.
+    fn from_raw(raw: *mut ALCdevice, loopback: Option<ALFormat>) -> ALResult<ALDevice> {
+        let mut device = ALDevice {
+            raw: raw,
+            loopback: loopback,
+            capabilities: ALCapabilities::default(),
+            pause_depth: AtomicUsize::new(0),
+        };
+
+        device.capabilities = try_rethrow!(ALCapabilities::query_device(&device));
+
+        Ok(device)
+    }
+
     pub fn open(name: Option<&str>) -> ALResult<Arc<ALDevice>> {
         let device = if let Some(name) = name {
             let c_name = try_throw!(CString::new(name));
@@ -38,26 +85,95 @@ impl ALDevice {
         };
 
         if device.is_null() {
-            check_alc_errors!();
+            let _ = ALError::check_device(ptr::null_mut());
+
+            throw!(ALError::DeviceOpenFailed { name: name.map(|name| name.to_owned()) });
+        }
 
-            panic!("Could not create OpenAL device");
+        Ok(Arc::new(try_rethrow!(ALDevice::from_raw(device, None))))
+    }
+
+    /// Open a device like `open`, falling back to "null audio" if that fails.
+    ///
+    /// In null audio mode the device is a loopback device that is never rendered,
+    /// so contexts, listeners, sources, buffers and effects all work normally,
+    /// but nothing is ever heard and sources never advance.
+    /// This lets an application keep running without sound when no output exists.
+    ///
+    /// Use `is_loopback` to check if the fallback was taken.
+    /// If the fallback isn't available either, the original error is returned.
+    pub fn open_or_null_audio(name: Option<&str>) -> ALResult<Arc<ALDevice>> {
+        match ALDevice::open(name) {
+            Ok(device) => Ok(device),
+            Err(err) => {
+                match ALLoopbackDevice::open(None, ALFormat::common_stereo32f(44100)) {
+                    Ok(loopback) => Ok(loopback.device()),
+                    Err(_) => Err(err),
+                }
+            }
         }
+    }
+
+    /// List all playback devices.
+    ///
+    /// Uses `ALC_ENUMERATE_ALL_EXT` if available, so every individual output is listed.
+    pub fn enumerate_playback() -> ALResult<Vec<DeviceInfo>> {
+        let (devices, default) = if NULL_DEVICE.capabilities.enumerate_all {
+            (try_rethrow!(NULL_DEVICE.get_multistring(ALC_ALL_DEVICES_SPECIFIER)),
+             try_rethrow!(NULL_DEVICE.get_string(ALC_DEFAULT_ALL_DEVICES_SPECIFIER)))
+        } else {
+            (try_rethrow!(NULL_DEVICE.get_multistring(ALC_DEVICE_SPECIFIER)),
+             try_rethrow!(NULL_DEVICE.get_string(ALC_DEFAULT_DEVICE_SPECIFIER)))
+        };
+
+        Ok(ALDevice::device_infos(devices, &default))
+    }
+
+    /// List all capture devices
+    pub fn enumerate_capture() -> ALResult<Vec<DeviceInfo>> {
+        let devices = try_rethrow!(NULL_DEVICE.get_multistring(ALC_CAPTURE_DEVICE_SPECIFIER));
+        let default = try_rethrow!(NULL_DEVICE.get_string(ALC_CAPTURE_DEFAULT_DEVICE_SPECIFIER));
 
-        Ok(Arc::new(ALDevice { raw: device }))
+        Ok(ALDevice::device_infos(devices, &default))
     }
 
+    /// Get the default playback device, if there is one
+    pub fn default_playback() -> ALResult<Option<DeviceInfo>> {
+        Ok(try_rethrow!(ALDevice::enumerate_playback()).into_iter().find(|info| info.is_default))
+    }
+
+    fn device_infos(devices: Vec<Cow<str>>, default: &str) -> Vec<DeviceInfo> {
+        devices.into_iter().filter(|name| !name.is_empty()).map(|name| {
+            DeviceInfo {
+                is_default: name == default,
+                name: name.into_owned(),
+            }
+        }).collect()
+    }
+
+    /// Returns the render format if this device was opened as an `ALLoopbackDevice`
+    #[inline(always)]
+    pub fn loopback_format(&self) -> Option<ALFormat> { self.loopback }
+
+    #[inline(always)]
+    pub fn is_loopback(&self) -> bool { self.loopback.is_some() }
+
+    /// Extensions supported by this device, queried once when it was opened
+    #[inline(always)]
+    pub fn capabilities(&self) -> &ALCapabilities { &self.capabilities }
+
     pub fn extension_present(&self, extension: &str) -> ALResult<bool> {
         let c_ext = try_throw!(CString::new(extension));
 
         let res = unsafe { alcIsExtensionPresent(self.raw, c_ext.as_ptr() as *const _) };
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcIsExtensionPresent);
 
         Ok(res == ALC_TRUE)
     }
 
     pub fn name(&self) -> ALResult<Cow<str>> {
-        if try_rethrow!(self.extension_present(ALC_ENUMERATE_ALL_EXT_NAME)) {
+        if self.capabilities.enumerate_all {
             self.get_string(ALC_ALL_DEVICES_SPECIFIER)
         } else {
             self.get_string(ALC_DEVICE_SPECIFIER)
@@ -69,17 +185,94 @@ impl ALDevice {
 
         unsafe { alcGetIntegerv(self.raw, param, 1, &mut res); }
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcGetIntegerv, param param);
 
         Ok(res)
     }
 
+    /// Attribute values currently in use by the device
+    pub fn attributes(&self) -> ALResult<ALContextAttributes> {
+        let size = try_rethrow!(self.get_integer(ALC_ATTRIBUTES_SIZE));
+
+        let mut attrs: Vec<ALCint> = vec![0; size as usize];
+
+        if size > 0 {
+            unsafe { alcGetIntegerv(self.raw, ALC_ALL_ATTRIBUTES, size, attrs.as_mut_ptr()); }
+
+            check_alc_errors!(fn alcGetIntegerv, param ALC_ALL_ATTRIBUTES);
+        }
+
+        ALContextAttributes::decode(&attrs)
+    }
+
+    /// Reconfigure a live device with new attributes.
+    ///
+    /// Contexts, sources and buffers created on this device stay valid.
+    /// Returns the attribute values that were actually applied.
+    ///
+    /// Requires `ALC_SOFT_HRTF`, which provides `alcResetDeviceSOFT`.
+    pub fn reset(&self, attributes: &ALContextAttributes) -> ALResult<ALContextAttributes> {
+        if !self.capabilities.hrtf {
+            throw!(ALError::Unsupported);
+        }
+
+        let attrs = try_rethrow!(attributes.encode(self));
+
+        if ALC_TRUE != unsafe { alcResetDeviceSOFT(self.raw, attrs.as_ptr()) } {
+            try_rethrow!(ALError::check_device(self.raw));
+
+            throw!(ALError::InvalidDevice);
+        }
+
+        self.attributes()
+    }
+
+    /// Check if the device is still connected, for example after a USB headset was unplugged.
+    ///
+    /// Once disconnected, a device stays disconnected and has to be replaced by a newly opened one.
+    pub fn is_connected(&self) -> ALResult<bool> {
+        if !self.capabilities.disconnect {
+            throw!(ALError::Unsupported);
+        }
+
+        Ok(try_rethrow!(self.get_integer(ALC_CONNECTED)) == ALC_TRUE as ALint)
+    }
+
+    /// Pause the mixer of the whole device.
+    ///
+    /// Source states are untouched, so sources that were playing continue
+    /// exactly where they were once the device is resumed.
+    pub fn pause(&self) -> ALResult<()> {
+        if !self.capabilities.pause_device {
+            throw!(ALError::Unsupported);
+        }
+
+        unsafe { alcDevicePauseSOFT(self.raw); }
+
+        try_rethrow!(ALError::check_device(self.raw));
+
+        Ok(())
+    }
+
+    /// Resume the mixer after `pause`
+    pub fn resume(&self) -> ALResult<()> {
+        if !self.capabilities.pause_device {
+            throw!(ALError::Unsupported);
+        }
+
+        unsafe { alcDeviceResumeSOFT(self.raw); }
+
+        try_rethrow!(ALError::check_device(self.raw));
+
+        Ok(())
+    }
+
     pub fn get_enum(&self, name: &str) -> ALResult<ALenum> {
         let c_str = try_throw!(CString::new(name));
 
         let res = unsafe { alcGetEnumValue(self.raw, c_str.as_ptr()) };
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcGetEnumValue);
 
         if res == 0 || res == -1 {
             throw!(ALError::InvalidEnum);
@@ -91,7 +284,7 @@ impl ALDevice {
     pub fn get_string(&self, param: ALenum) -> ALResult<Cow<str>> {
         let c_str = unsafe { alcGetString(self.raw, param) };
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcGetString, param param);
 
         Ok(unsafe { CStr::from_ptr(c_str).to_string_lossy() })
     }
@@ -99,7 +292,7 @@ impl ALDevice {
     pub fn get_stringi(&self, param: ALenum, i: ALint) -> ALResult<Cow<str>> {
         let c_str = unsafe { alcGetStringiSOFT(self.raw, param, i) };
 
-        check_alc_errors!();
+        check_alc_errors!(fn alcGetStringiSOFT, param param);
 
         Ok(unsafe { CStr::from_ptr(c_str).to_string_lossy() })
     }
@@ -127,7 +320,7 @@ impl ALDevice {
         if MULTISTRING_PARAMS.contains(&param) {
             let mut c_strs = unsafe { alcGetString(self.raw, param) };
 
-            check_alc_errors!();
+            check_alc_errors!(fn alcGetString, param param);
 
             let mut results = Vec::new();
 
@@ -154,7 +347,12 @@ impl ALDevice {
 
 pub trait ALDeviceArc {
     fn create_context(&self) -> ALResult<Arc<ALContext>>;
+    fn create_context_with(&self, attributes: &ALContextAttributes) -> ALResult<Arc<ALContext>>;
     fn create_listener(&self) -> ALResult<Arc<ALListener>>;
+    /// Pause the device until the returned guard is dropped
+    ///
+    /// Guards can be nested, in which case the device is resumed when the outermost one ends.
+    fn paused(&self) -> ALResult<PausedDevice>;
 }
 
 impl ALDeviceArc for Arc<ALDevice> {
@@ -162,17 +360,313 @@ impl ALDeviceArc for Arc<ALDevice> {
         ALContext::create_from_device(self.clone())
     }
 
+    fn create_context_with(&self, attributes: &ALContextAttributes) -> ALResult<Arc<ALContext>> {
+        ALContext::create_with_attributes(self.clone(), attributes)
+    }
+
     fn create_listener(&self) -> ALResult<Arc<ALListener>> {
         Ok(ALListener::new(try_rethrow!(self.create_context())))
     }
+
+    fn paused(&self) -> ALResult<PausedDevice> {
+        // Nested guards leave pausing and resuming to the outermost one
+        if self.pause_depth.fetch_add(1, Ordering::SeqCst) == 0 {
+            if let Err(err) = self.pause() {
+                self.pause_depth.fetch_sub(1, Ordering::SeqCst);
+
+                return Err(err);
+            }
+        }
+
+        Ok(PausedDevice { device: self.clone(), resumed: false })
+    }
+}
+
+/// Keeps a device paused while alive, see `ALDeviceArc::paused`
+pub struct PausedDevice {
+    device: Arc<ALDevice>,
+    resumed: bool,
+}
+
+impl PausedDevice {
+    #[inline(always)]
+    pub fn device(&self) -> Arc<ALDevice> { self.device.clone() }
+
+    /// Resume the device now, returning any error instead of discarding it on drop
+    ///
+    /// The device stays paused while other guards on it are alive.
+    pub fn resume(mut self) -> ALResult<()> {
+        self.resumed = true;
+
+        self.end()
+    }
+
+    fn end(&self) -> ALResult<()> {
+        if self.device.pause_depth.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.device.resume()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for PausedDevice {
+    fn drop(&mut self) {
+        if !self.resumed {
+            ALError::report_drop_error(self.end());
+        }
+    }
 }
 
 impl Drop for ALDevice {
     fn drop(&mut self) {
         if !self.raw.is_null() {
-            unsafe { alcCloseDevice(self.raw); }
+            // If closing failed the device is still alive, and the reason is queued on it
+            if ALC_TRUE != unsafe { alcCloseDevice(self.raw) } {
+                ALError::report_drop_error(ALError::check_device(self.raw));
+            }
+        }
+    }
+}
+
+
+/// Loopback device for rendering the mix into memory instead of to an audio output
+///
+/// This dereferences to `Arc<ALDevice>`, so contexts, listeners, sources and buffers
+/// are created exactly as with a normal device. Nothing is mixed until `render` is called.
+pub struct ALLoopbackDevice(Arc<ALDevice>);
+
+impl ALLoopbackDevice {
+    /// Open a loopback device that renders in the given format.
+    ///
+    /// Returns `ALError::Unsupported` if `ALC_SOFT_loopback` is not available
+    /// or the format cannot be rendered.
+    pub fn open(name: Option<&str>, format: ALFormat) -> ALResult<ALLoopbackDevice> {
+        if !NULL_DEVICE.capabilities.loopback {
+            throw!(ALError::Unsupported);
+        }
 
-            ALError::check_alc().unwrap();
+        let device = if let Some(name) = name {
+            let c_name = try_throw!(CString::new(name));
+
+            unsafe { alcLoopbackOpenDeviceSOFT(c_name.as_ptr() as *const ALchar) }
+        } else {
+            unsafe { alcLoopbackOpenDeviceSOFT(ptr::null()) }
+        };
+
+        if device.is_null() {
+            let _ = ALError::check_device(ptr::null_mut());
+
+            throw!(ALError::DeviceOpenFailed { name: name.map(|name| name.to_owned()) });
+        }
+
+        // Take ownership first so the device is closed if the format is rejected
+        let device = Arc::new(try_rethrow!(ALDevice::from_raw(device, Some(format))));
+
+        if !try_rethrow!(ALLoopbackDevice::is_render_format_supported(&device, format)) {
+            throw!(ALError::Unsupported);
+        }
+
+        Ok(ALLoopbackDevice(device))
+    }
+
+    /// Check if the loopback device can render in the given format
+    pub fn is_render_format_supported(device: &ALDevice, format: ALFormat) -> ALResult<bool> {
+        let res = unsafe { alcIsRenderFormatSupportedSOFT(device.raw, format.sample_rate as ALCsizei, format.channels(), format.sample_type()) };
+
+        try_rethrow!(ALError::check_device(device.raw));
+
+        Ok(res == ALC_TRUE)
+    }
+
+    /// Format the mix is rendered in
+    #[inline]
+    pub fn format(&self) -> ALFormat {
+        self.0.loopback.expect("Loopback device without a render format")
+    }
+
+    /// Size in bytes of a single rendered frame
+    #[inline]
+    pub fn frame_size(&self) -> usize {
+        self.format().frame_size()
+    }
+
+    /// Render `frames.len()` frames of the mix into `frames`.
+    ///
+    /// `F` must have the channels and sample type of the render format, such as `[f32; 2]` for `Stereo`/`Float`,
+    /// otherwise `ALError::InvalidValue` is returned.
+    ///
+    /// A context must have been created on this device first.
+    pub fn render<F: ALFrame>(&self, frames: &mut [F]) -> ALResult<()> {
+        if !F::matches(&self.format()) {
+            throw!(ALError::InvalidValue);
+        }
+
+        unsafe { alcRenderSamplesSOFT(self.0.raw, frames.as_mut_ptr() as *mut c_void, frames.len() as ALCsizei); }
+
+        try_rethrow!(ALError::check_device(self.0.raw));
+
+        Ok(())
+    }
+
+    /// Get the underlying device, for sharing it between owners
+    #[inline(always)]
+    pub fn device(&self) -> Arc<ALDevice> { self.0.clone() }
+}
+
+impl Deref for ALLoopbackDevice {
+    type Target = Arc<ALDevice>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Arc<ALDevice> { &self.0 }
+}
+
+/// Audio capture (recording) device
+///
+/// Captured samples are always delivered in the `ALFormat` given when the device was opened.
+#[derive(Eq, PartialEq)]
+pub struct ALCaptureDevice {
+    raw: *mut ALCdevice,
+    format: ALFormat,
+}
+
+unsafe impl Sync for ALCaptureDevice {}
+
+unsafe impl Send for ALCaptureDevice {}
+
+impl ALCaptureDevice {
+    #[inline(always)]
+    pub unsafe fn raw(&self) -> *mut ALCdevice { self.raw }
+
+    /// Open a capture device, with `None` being the default capture device.
+    ///
+    /// `buffer_frames` is the number of frames OpenAL should be able to hold
+    /// before old samples are overwritten.
+    pub fn open(name: Option<&str>, format: ALFormat, buffer_frames: usize) -> ALResult<Arc<ALCaptureDevice>> {
+        let internal_format = format.internal_format();
+
+        let device = if let Some(name) = name {
+            let c_name = try_throw!(CString::new(name));
+
+            unsafe { alcCaptureOpenDevice(c_name.as_ptr() as *const ALchar, format.sample_rate as ALCuint, internal_format, buffer_frames as ALCsizei) }
+        } else {
+            unsafe { alcCaptureOpenDevice(ptr::null(), format.sample_rate as ALCuint, internal_format, buffer_frames as ALCsizei) }
+        };
+
+        if device.is_null() {
+            let _ = ALError::check_device(ptr::null_mut());
+
+            throw!(ALError::DeviceOpenFailed { name: name.map(|name| name.to_owned()) });
         }
+
+        Ok(Arc::new(ALCaptureDevice { raw: device, format: format }))
+    }
+
+    /// Format of the captured samples
+    #[inline(always)]
+    pub fn format(&self) -> ALFormat { self.format }
+
+    pub fn name(&self) -> ALResult<Cow<str>> {
+        let c_str = unsafe { alcGetString(self.raw, ALC_CAPTURE_DEVICE_SPECIFIER) };
+
+        try_rethrow!(ALError::check_device(self.raw));
+
+        Ok(unsafe { CStr::from_ptr(c_str).to_string_lossy() })
+    }
+
+    pub fn start(&self) -> ALResult<()> {
+        unsafe { alcCaptureStart(self.raw); }
+
+        try_rethrow!(ALError::check_device(self.raw));
+
+        Ok(())
+    }
+
+    pub fn stop(&self) -> ALResult<()> {
+        unsafe { alcCaptureStop(self.raw); }
+
+        try_rethrow!(ALError::check_device(self.raw));
+
+        Ok(())
+    }
+
+    /// Returns the number of captured frames that are ready to be read
+    pub fn available_samples(&self) -> ALResult<usize> {
+        let mut res: ALint = 0;
+
+        unsafe { alcGetIntegerv(self.raw, ALC_CAPTURE_SAMPLES, 1, &mut res); }
+
+        try_rethrow!(ALError::check_device(self.raw));
+
+        Ok(res as usize)
+    }
+
+    /// Read captured frames into `frames`, returning how many frames were written.
+    ///
+    /// Samples are delivered as stored at the depth of the capture format, so `F` must have its channels
+    /// and `u8`, `i16` or `f32` samples for `Int8`, `Int16` and `Float32`, such as `i16` for `Mono`/`Int16`
+    /// or `[f32; 2]` for `Stereo`/`Float32`, otherwise `ALError::InvalidValue` is returned.
+    ///
+    /// At most `available_samples()` frames are read, so this never blocks.
+    pub fn capture<F: ALFrame>(&self, frames: &mut [F]) -> ALResult<usize> {
+        if F::channels().count() != self.format.channels.count() ||
+           <F::Sample as ALSample>::sample_type() != self.format.depth.sample_type() ||
+           mem::size_of::<F>() != self.format.storage_frame_size() {
+            throw!(ALError::InvalidValue);
+        }
+
+        let count = ::std::cmp::min(try_rethrow!(self.available_samples()), frames.len());
+
+        if count > 0 {
+            unsafe { alcCaptureSamples(self.raw, frames.as_mut_ptr() as *mut c_void, count as ALCsizei); }
+
+            try_rethrow!(ALError::check_device(self.raw));
+        }
+
+        Ok(count)
+    }
+}
+
+impl Drop for ALCaptureDevice {
+    fn drop(&mut self) {
+        if ALC_TRUE != unsafe { alcCaptureCloseDevice(self.raw) } {
+            ALError::report_drop_error(ALError::check_device(self.raw));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{ALBuffer, ALSource};
+
+    #[test]
+    fn loopback_renders_the_mix() {
+        let device = ALLoopbackDevice::open(None, ALFormat::common_stereo32f(44100)).unwrap();
+        let listener = device.create_listener().unwrap();
+
+        let mut frames = vec![[1.0f32; 2]; 256];
+
+        // Nothing is playing yet
+        device.render(&mut frames).unwrap();
+
+        assert!(frames.iter().all(|frame| *frame == [0.0, 0.0]));
+
+        let tone: Vec<f32> = (0..4410).map(|i| if i % 100 < 50 { 0.5 } else { -0.5 }).collect();
+
+        let buffer = ALBuffer::from_frames(&tone, 44100).unwrap();
+        let source = ALSource::new(listener.clone()).unwrap();
+
+        source.queue_buffers(vec![buffer].into_iter()).unwrap();
+        source.play().unwrap();
+
+        device.render(&mut frames).unwrap();
+
+        assert!(frames.iter().any(|frame| frame[0] != 0.0 || frame[1] != 0.0));
+
+        // Frames that don't match the render format are refused
+        let mut mono = vec![0i16; 16];
+
+        assert!(device.render(&mut mono).is_err());
     }
 }
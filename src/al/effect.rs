@@ -11,15 +11,21 @@ use super::ALObject;
 
 pub struct ALEffect(ALuint, Arc<ALListener>);
 
-impl_simple_alobject!(simple ALEffect, alIsEffect);
+impl_simple_alobject!(simple ALEffect, alIsEffect, context 1);
 
 impl ALEffect {
     pub fn new(listener: Arc<ALListener>) -> ALResult<Arc<ALEffect>> {
+        try_rethrow!(listener.ensure_current());
+
+        if !listener.capabilities().efx {
+            throw!(ALError::Unsupported);
+        }
+
         let mut effect = 0;
 
         unsafe { alGenEffects(1, &mut effect); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGenEffects);
 
         Ok(Arc::new(ALEffect(effect, listener)))
     }
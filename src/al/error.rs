@@ -5,12 +5,23 @@ use std::io;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::ffi::{NulError};
 use std::string::FromUtf8Error;
+use std::panic;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{Ordering, AtomicBool, ATOMIC_BOOL_INIT};
 
-use trace_error::TraceResult;
+use trace_error::{TraceError, TraceResult};
 
 pub type ALResult<T> = TraceResult<T, ALError>;
 
+type DropErrorHook = Arc<Fn(&TraceError<ALError>) + Send + Sync>;
+
+lazy_static! {
+    static ref DROP_ERROR_HOOK: Mutex<Option<DropErrorHook>> = Mutex::new(None);
+    static ref DROP_ERRORS: Mutex<Vec<TraceError<ALError>>> = Mutex::new(Vec::new());
+}
+
 #[derive(Debug)]
 pub enum ALError {
     //OpenAL Errors
@@ -29,15 +40,104 @@ pub enum ALError {
     FromUtf8Error(FromUtf8Error),
     //Other errors
     Unsupported,
+    /// No device could be opened, with the requested device name if there was one
+    DeviceOpenFailed { name: Option<String> },
+    /// The device refused to create a context
+    ContextCreationFailed,
+    /// The context could not be made current
+    MakeCurrentFailed,
+    /// Another error, with the OpenAL call it was found after
+    Call(Box<ALError>, ALCall),
 }
 
 static mut CHECK_DISABLED: AtomicBool = ATOMIC_BOOL_INIT;
 
+thread_local! {
+    // Number of live `ErrorCheckSuspend` guards on this thread
+    static CHECK_SUSPENDED: Cell<usize> = Cell::new(0);
+}
+
+/// Defers error checks on the current thread while alive.
+///
+/// OpenAL keeps the first error that occurred until it is checked, so when the outermost guard
+/// is finished or dropped, the first error accumulated by the context current at that point is surfaced.
+/// `finish` returns it directly, while dropping the guard reports it like errors in `Drop`,
+/// see `ALError::take_drop_errors`.
+///
+/// The error state belongs to each context rather than to the guard, so not every error is caught:
+/// errors raised while another context was current, such as through `ALContext::bind`,
+/// are left on that context, and another thread checking the same context may take an error first.
+///
+/// ```ignore
+/// let suspend = ErrorCheckSuspend::new();
+///
+/// for source in &sources {
+///     try_rethrow!(source.set_position(position));
+/// }
+///
+/// try_rethrow!(suspend.finish());
+/// ```
+pub struct ErrorCheckSuspend {
+    finished: bool,
+    // The guard only affects the thread it was created on
+    _thread: PhantomData<*const ()>,
+}
+
+impl ErrorCheckSuspend {
+    pub fn new() -> ErrorCheckSuspend {
+        CHECK_SUSPENDED.with(|depth| depth.set(depth.get() + 1));
+
+        ErrorCheckSuspend { finished: false, _thread: PhantomData }
+    }
+
+    /// Resume error checks, returning the first error that occurred while suspended
+    pub fn finish(mut self) -> ALResult<()> {
+        self.finished = true;
+
+        ErrorCheckSuspend::resume()
+    }
+
+    fn resume() -> ALResult<()> {
+        // This runs on drop, so treat a torn down thread-local as no longer suspended instead of panicking
+        let depth = CHECK_SUSPENDED.try_with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get()
+        }).unwrap_or(0);
+
+        // Nested guards leave the check to the outermost one
+        if depth == 0 { ALError::check() } else { Ok(()) }
+    }
+}
+
+impl Drop for ErrorCheckSuspend {
+    fn drop(&mut self) {
+        if !self.finished {
+            ALError::report_drop_error(ErrorCheckSuspend::resume());
+        }
+    }
+}
+
 
 #[macro_export]
 macro_rules! check_al_errors {
     () => {try_rethrow!(ALError::check())};
 
+    (fn $func:ident, object $object:expr, param $param:expr) => {
+        try_rethrow!(ALError::check_call(ALCall::new(stringify!($func), Some($object), Some($param))))
+    };
+
+    (fn $func:ident, object $object:expr) => {
+        try_rethrow!(ALError::check_call(ALCall::new(stringify!($func), Some($object), None)))
+    };
+
+    (fn $func:ident, param $param:expr) => {
+        try_rethrow!(ALError::check_call(ALCall::new(stringify!($func), None, Some($param))))
+    };
+
+    (fn $func:ident) => {
+        try_rethrow!(ALError::check_call(ALCall::new(stringify!($func), None, None)))
+    };
+
     ($ret:expr) => {
         try_rethrow!(ALError::check());
 
@@ -49,6 +149,14 @@ macro_rules! check_al_errors {
 macro_rules! check_alc_errors {
     () => {try_rethrow!(ALError::check_alc())};
 
+    (fn $func:ident, param $param:expr) => {
+        try_rethrow!(ALError::check_alc_call(ALCall::new(stringify!($func), None, Some($param))))
+    };
+
+    (fn $func:ident) => {
+        try_rethrow!(ALError::check_alc_call(ALCall::new(stringify!($func), None, None)))
+    };
+
     ($ret:expr) => {
         try_rethrow!(ALError::check_alc());
 
@@ -56,23 +164,48 @@ macro_rules! check_alc_errors {
     };
 }
 
+/// Describes the OpenAL call after which an error was found
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ALCall {
+    /// Name of the OpenAL function
+    pub function: &'static str,
+    /// Name of the object the function was called on, as given by `ALObject::raw`
+    pub object: Option<ALuint>,
+    /// Parameter enum given to the function
+    pub param: Option<ALenum>,
+}
+
+impl ALCall {
+    #[inline]
+    pub fn new(function: &'static str, object: Option<ALuint>, param: Option<ALenum>) -> ALCall {
+        ALCall { function: function, object: object, param: param }
+    }
+}
+
+impl Display for ALCall {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        try!(write!(f, "{}", self.function));
+
+        if let Some(object) = self.object {
+            try!(write!(f, " on object {}", object));
+        }
+
+        if let Some(param) = self.param {
+            try!(write!(f, " with parameter 0x{:X}", param));
+        }
+
+        Ok(())
+    }
+}
+
 impl ALError {
     /// Check if there are any errors in the OpenAL error queue
     ///
     /// If check was disabled, this functions returns `Ok(())` immediately
     pub fn check() -> ALResult<()> {
-        if unsafe { !CHECK_DISABLED.load(Ordering::SeqCst) } {
-            let err = unsafe { alGetError() };
-
-            if err != AL_NO_ERROR {
-                throw!(match err {
-                    AL_INVALID_NAME => ALError::InvalidName,
-                    AL_INVALID_ENUM => ALError::InvalidEnum,
-                    AL_INVALID_VALUE => ALError::InvalidValue,
-                    AL_INVALID_OPERATION => ALError::InvalidOperation,
-                    AL_OUT_OF_MEMORY => ALError::OutOfMemory,
-                    _ => ALError::UnknownError(err),
-                });
+        if ALError::checks_enabled() {
+            if let Some(err) = ALError::pending() {
+                throw!(err);
             }
 
             try_rethrow!(ALError::check_alc());
@@ -81,42 +214,197 @@ impl ALError {
         Ok(())
     }
 
+    /// Like `check`, but records the call the error was found after
+    pub fn check_call(call: ALCall) -> ALResult<()> {
+        if ALError::checks_enabled() {
+            if let Some(err) = ALError::pending() {
+                throw!(ALError::Call(Box::new(err), call));
+            }
+
+            try_rethrow!(ALError::check_alc_call(call));
+        }
+
+        Ok(())
+    }
+
+    /// Check if there are any errors in the ALC error queue of the device owning the current context
     pub fn check_alc() -> ALResult<()> {
-        if unsafe { !CHECK_DISABLED.load(Ordering::SeqCst) } {
-            let ctx = unsafe { alcGetCurrentContext() };
-
-            if !ctx.is_null() {
-                let device = unsafe { alcGetContextsDevice(ctx) };
-
-                if !device.is_null() {
-                    let err = unsafe { alcGetError(device) };
-
-                    if err != ALC_NO_ERROR {
-                        throw!(match err {
-                            ALC_INVALID_DEVICE => ALError::InvalidDevice,
-                            ALC_INVALID_CONTEXT => ALError::InvalidContext,
-                            ALC_INVALID_ENUM => ALError::InvalidEnum,
-                            ALC_INVALID_VALUE => ALError::InvalidValue,
-                            ALC_OUT_OF_MEMORY => ALError::OutOfMemory,
-                            _ => ALError::UnknownContextError(err),
-                        });
-                    }
-                }
+        if ALError::checks_enabled() {
+            if let Some(err) = ALError::pending_alc(ALError::current_device()) {
+                throw!(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like `check_alc`, but records the call the error was found after
+    pub fn check_alc_call(call: ALCall) -> ALResult<()> {
+        if ALError::checks_enabled() {
+            if let Some(err) = ALError::pending_alc(ALError::current_device()) {
+                throw!(ALError::Call(Box::new(err), call));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if there are any errors in the ALC error queue of a specific device
+    ///
+    /// This is required for devices that never own a context, like capture devices,
+    /// and for failures that are reported on the null device.
+    pub fn check_device(device: *mut ALCdevice) -> ALResult<()> {
+        if ALError::checks_enabled() {
+            if let Some(err) = ALError::pending_alc(Some(device)) {
+                throw!(err);
             }
         }
 
         Ok(())
     }
 
+    fn pending() -> Option<ALError> {
+        let err = unsafe { alGetError() };
+
+        match err {
+            AL_NO_ERROR => None,
+            AL_INVALID_NAME => Some(ALError::InvalidName),
+            AL_INVALID_ENUM => Some(ALError::InvalidEnum),
+            AL_INVALID_VALUE => Some(ALError::InvalidValue),
+            AL_INVALID_OPERATION => Some(ALError::InvalidOperation),
+            AL_OUT_OF_MEMORY => Some(ALError::OutOfMemory),
+            _ => Some(ALError::UnknownError(err)),
+        }
+    }
+
+    fn current_device() -> Option<*mut ALCdevice> {
+        let ctx = unsafe { alcGetCurrentContext() };
+
+        if ctx.is_null() { None } else {
+            let device = unsafe { alcGetContextsDevice(ctx) };
+
+            if device.is_null() { None } else { Some(device) }
+        }
+    }
+
+    fn pending_alc(device: Option<*mut ALCdevice>) -> Option<ALError> {
+        let err = match device {
+            Some(device) => unsafe { alcGetError(device) },
+            None => return None,
+        };
+
+        match err {
+            ALC_NO_ERROR => None,
+            ALC_INVALID_DEVICE => Some(ALError::InvalidDevice),
+            ALC_INVALID_CONTEXT => Some(ALError::InvalidContext),
+            ALC_INVALID_ENUM => Some(ALError::InvalidEnum),
+            ALC_INVALID_VALUE => Some(ALError::InvalidValue),
+            ALC_OUT_OF_MEMORY => Some(ALError::OutOfMemory),
+            _ => Some(ALError::UnknownContextError(err)),
+        }
+    }
+
+    /// The underlying error, without any call information
+    pub fn kind(&self) -> &ALError {
+        match *self {
+            ALError::Call(ref err, _) => err.kind(),
+            _ => self,
+        }
+    }
+
+    /// The OpenAL call this error was found after, if known
+    pub fn call(&self) -> Option<&ALCall> {
+        match *self {
+            ALError::Call(_, ref call) => Some(call),
+            _ => None,
+        }
+    }
+
+    /// Name of the OpenAL function this error was found after, if known
+    #[inline]
+    pub fn function(&self) -> Option<&'static str> {
+        self.call().map(|call| call.function)
+    }
+
+    /// Name of the object the failing call was made on, if known
+    #[inline]
+    pub fn object(&self) -> Option<ALuint> {
+        self.call().and_then(|call| call.object)
+    }
+
+    /// Parameter enum given to the failing call, if known
+    #[inline]
+    pub fn param(&self) -> Option<ALenum> {
+        self.call().and_then(|call| call.param)
+    }
+
+    /// Register a hook that receives every error found while dropping AL objects.
+    ///
+    /// While a hook is registered, errors are no longer queued for `take_drop_errors`.
+    /// The hook may be called from any thread, and panics inside it are discarded.
+    pub fn set_drop_error_hook<F>(hook: F) where F: Fn(&TraceError<ALError>) + Send + Sync + 'static {
+        if let Ok(mut current) = DROP_ERROR_HOOK.lock() {
+            *current = Some(Arc::new(hook));
+        }
+    }
+
+    /// Remove the drop error hook, going back to queueing errors
+    pub fn clear_drop_error_hook() {
+        if let Ok(mut current) = DROP_ERROR_HOOK.lock() {
+            *current = None;
+        }
+    }
+
+    /// Take all queued errors that were found while dropping AL objects
+    pub fn take_drop_errors() -> Vec<TraceError<ALError>> {
+        match DROP_ERRORS.lock() {
+            Ok(mut errors) => errors.drain(..).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Report the result of destroying an AL object without ever panicking.
+    ///
+    /// Errors go to the drop error hook if one is registered, otherwise they are queued for `take_drop_errors`.
+    pub fn report_drop_error(result: ALResult<()>) {
+        if let Err(err) = result {
+            // Call the hook without holding the lock, as it may drop AL objects or replace itself
+            let hook = match DROP_ERROR_HOOK.lock() {
+                Ok(hook) => hook.clone(),
+                Err(_) => None,
+            };
+
+            if let Some(hook) = hook {
+                // We may already be unwinding, so a panicking hook must not escape
+                let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| hook(&err)));
+
+                return;
+            }
+
+            if let Ok(mut errors) = DROP_ERRORS.lock() {
+                errors.push(err);
+            }
+        }
+    }
+
+    /// Returns false if checks are disabled globally or suspended on this thread
+    #[inline]
+    pub fn checks_enabled() -> bool {
+        // Reached from every drop, which may happen while thread-locals are being torn down
+        unsafe { !CHECK_DISABLED.load(Ordering::SeqCst) } && CHECK_SUSPENDED.try_with(|depth| depth.get() == 0).unwrap_or(true)
+    }
+
     /// Disable the `check` function, causing it to return `Ok(())` instantly every time.
     ///
     /// The only real reason to do this is to improve performance in very hot loops,
     /// just don't forget to re-enable it and check as soon as possible.
+    #[deprecated(note = "affects every thread and loses errors, use `ErrorCheckSuspend` instead")]
     pub unsafe fn disable_check() {
         CHECK_DISABLED.store(true, Ordering::SeqCst);
     }
 
     /// Enable the `check` function, resuming its normal behavior after it had been disabled
+    #[deprecated(note = "affects every thread and loses errors, use `ErrorCheckSuspend` instead")]
     pub unsafe fn enable_check() {
         CHECK_DISABLED.store(false, Ordering::SeqCst);
     }
@@ -152,7 +440,11 @@ impl From<ALError> for io::Error {
 
 impl Display for ALError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "{}", self.description())
+        match *self {
+            ALError::DeviceOpenFailed { name: Some(ref name) } => write!(f, "{}: {}", self.description(), name),
+            ALError::Call(ref err, ref call) => write!(f, "{} in {}", err, call),
+            _ => write!(f, "{}", self.description())
+        }
     }
 }
 
@@ -172,6 +464,10 @@ impl Error for ALError {
             ALError::UnknownError(_) => "Unknown OpenAL Error",
             ALError::UnknownContextError(_) => "Unknown OpenAL Context Error",
             ALError::Unsupported => "Unsupported Feature",
+            ALError::DeviceOpenFailed { .. } => "Could not open OpenAL device",
+            ALError::ContextCreationFailed => "Could not create OpenAL context",
+            ALError::MakeCurrentFailed => "Could not make OpenAL context current",
+            ALError::Call(ref err, _) => err.description(),
         }
     }
 }
\ No newline at end of file
@@ -1,8 +1,17 @@
 use als::all::*;
 
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use trace_error::TraceError;
+
+use super::error::*;
+use super::state::*;
+use super::capabilities::*;
+
 pub type ALSampleRate = u32;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ALChannels {
     /// Monophonic sample
     Mono,
@@ -20,7 +29,39 @@ pub enum ALChannels {
     Point71,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+impl ALChannels {
+    /// Number of individual channels in a single frame
+    pub fn count(&self) -> usize {
+        match *self {
+            ALChannels::Mono => 1,
+            ALChannels::Stereo => 2,
+            ALChannels::Quad => 4,
+            ALChannels::Rear => 2,
+            ALChannels::Point51 => 6,
+            ALChannels::Point61 => 7,
+            ALChannels::Point71 => 8,
+        }
+    }
+
+    fn token(&self) -> &'static str {
+        match *self {
+            ALChannels::Mono => "mono",
+            ALChannels::Stereo => "stereo",
+            ALChannels::Quad => "quad",
+            ALChannels::Rear => "rear",
+            ALChannels::Point51 => "51chn",
+            ALChannels::Point61 => "61chn",
+            ALChannels::Point71 => "71chn",
+        }
+    }
+}
+
+static ALL_CHANNELS: [ALChannels; 7] = [
+    ALChannels::Mono, ALChannels::Stereo, ALChannels::Quad, ALChannels::Rear,
+    ALChannels::Point51, ALChannels::Point61, ALChannels::Point71,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ALBitDepth {
     /// 8-bit integer format
     ///
@@ -36,7 +77,27 @@ pub enum ALBitDepth {
     Float32,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+impl ALBitDepth {
+    /// Size in bytes of a single sample stored at this depth
+    pub fn size(&self) -> usize {
+        match *self {
+            ALBitDepth::Int8 => 1,
+            ALBitDepth::Int16 => 2,
+            ALBitDepth::Float32 => 4,
+        }
+    }
+
+    /// Sample type that OpenAL stores this bit-depth as internally
+    pub fn sample_type(&self) -> ALSampleType {
+        match *self {
+            ALBitDepth::Int8 => ALSampleType::UnsignedByte,
+            ALBitDepth::Int16 => ALSampleType::Short,
+            ALBitDepth::Float32 => ALSampleType::Float,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ALSampleType {
     Byte,
     UnsignedByte,
@@ -50,7 +111,50 @@ pub enum ALSampleType {
     UnsignedByte3,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+impl ALSampleType {
+    /// Size in bytes of a single sample of this type
+    pub fn size(&self) -> usize {
+        match *self {
+            ALSampleType::Byte | ALSampleType::UnsignedByte => 1,
+            ALSampleType::Short | ALSampleType::UnsignedShort => 2,
+            ALSampleType::Byte3 | ALSampleType::UnsignedByte3 => 3,
+            ALSampleType::Int | ALSampleType::UnsignedInt | ALSampleType::Float => 4,
+            ALSampleType::Double => 8,
+        }
+    }
+
+    /// Internal bit-depth that stores this sample type without needless loss or waste
+    pub fn depth(&self) -> ALBitDepth {
+        match *self {
+            ALSampleType::Byte | ALSampleType::UnsignedByte => ALBitDepth::Int8,
+            ALSampleType::Short | ALSampleType::UnsignedShort => ALBitDepth::Int16,
+            _ => ALBitDepth::Float32,
+        }
+    }
+
+    fn token(&self) -> &'static str {
+        match *self {
+            ALSampleType::Byte => "s8",
+            ALSampleType::UnsignedByte => "8",
+            ALSampleType::Short => "16",
+            ALSampleType::UnsignedShort => "u16",
+            ALSampleType::Int => "32",
+            ALSampleType::UnsignedInt => "u32",
+            ALSampleType::Float => "32f",
+            ALSampleType::Double => "64f",
+            ALSampleType::Byte3 => "24",
+            ALSampleType::UnsignedByte3 => "u24",
+        }
+    }
+}
+
+static ALL_SAMPLE_TYPES: [ALSampleType; 10] = [
+    ALSampleType::Byte, ALSampleType::UnsignedByte, ALSampleType::Short, ALSampleType::UnsignedShort,
+    ALSampleType::Int, ALSampleType::UnsignedInt, ALSampleType::Float, ALSampleType::Double,
+    ALSampleType::Byte3, ALSampleType::UnsignedByte3,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ALFormat {
     /// Internal bit-depth
     pub depth: ALBitDepth,
@@ -63,6 +167,107 @@ pub struct ALFormat {
 }
 
 impl ALFormat {
+    /// Create a format storing `sample_type` at its natural depth
+    ///
+    /// Returns `ALError::InvalidValue` for a sample rate of zero.
+    pub fn new(channels: ALChannels, sample_type: ALSampleType, sample_rate: ALSampleRate) -> ALResult<ALFormat> {
+        let format = ALFormat {
+            depth: sample_type.depth(),
+            channels: channels,
+            sample_rate: sample_rate,
+            sample_type: sample_type,
+        };
+
+        try_rethrow!(format.validate());
+
+        Ok(format)
+    }
+
+    /// Check that the fields don't contradict each other
+    ///
+    /// The depth can't be narrower than the natural depth of the sample type, such as `Int8` for `Double` samples,
+    /// as storing the samples would discard most of them. Wider depths are fine, and the sample rate can't be zero.
+    pub fn validate(&self) -> ALResult<()> {
+        if self.depth.size() < self.sample_type.depth().size() || self.sample_rate == 0 {
+            throw!(ALError::InvalidValue);
+        }
+
+        Ok(())
+    }
+
+    /// Check if the current context can store buffer data in this format
+    ///
+    /// Without `AL_SOFT_buffer_samples`, which OpenAL Soft dropped in 1.18, buffer data is stored as given,
+    /// so only formats at their natural depth that have a `buffer_data_format` are supported.
+    pub fn is_supported(&self) -> ALResult<bool> {
+        if try_rethrow!(ALCapabilities::current()).buffer_samples {
+            let res = unsafe { alIsBufferFormatSupportedSOFT(self.internal_format()) };
+
+            check_al_errors!(fn alIsBufferFormatSupportedSOFT, param self.internal_format());
+
+            Ok(res == AL_TRUE)
+        } else if self.depth != self.sample_type.depth() {
+            Ok(false)
+        } else {
+            Ok(try_rethrow!(self.buffer_data_format()).is_some())
+        }
+    }
+
+    /// Format enum for uploading data in this format with `alBufferData`, if the current context knows one
+    ///
+    /// Only `UnsignedByte`, `Short` and `Float` samples have one, and `Float` samples or layouts
+    /// beyond stereo also need `AL_EXT_FLOAT32` or `AL_EXT_MCFORMATS`.
+    pub fn buffer_data_format(&self) -> ALResult<Option<ALenum>> {
+        let bits = match self.sample_type {
+            ALSampleType::UnsignedByte => "8",
+            ALSampleType::Short => "16",
+            ALSampleType::Float => "32",
+            _ => return Ok(None),
+        };
+
+        let name = match (self.channels, self.sample_type) {
+            (ALChannels::Mono, ALSampleType::Float) => "AL_FORMAT_MONO_FLOAT32".to_owned(),
+            (ALChannels::Stereo, ALSampleType::Float) => "AL_FORMAT_STEREO_FLOAT32".to_owned(),
+            (ALChannels::Mono, _) => format!("AL_FORMAT_MONO{}", bits),
+            (ALChannels::Stereo, _) => format!("AL_FORMAT_STEREO{}", bits),
+            (ALChannels::Quad, _) => format!("AL_FORMAT_QUAD{}", bits),
+            (ALChannels::Rear, _) => format!("AL_FORMAT_REAR{}", bits),
+            (ALChannels::Point51, _) => format!("AL_FORMAT_51CHN{}", bits),
+            (ALChannels::Point61, _) => format!("AL_FORMAT_61CHN{}", bits),
+            (ALChannels::Point71, _) => format!("AL_FORMAT_71CHN{}", bits),
+        };
+
+        // Extension formats are only known by name to implementations that support them
+        match ALState::get_enum(&name) {
+            Ok(format) => Ok(Some(format)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Size in bytes of a single sample frame of data in this format
+    #[inline]
+    pub fn frame_size(&self) -> usize {
+        self.channels.count() * self.sample_type.size()
+    }
+
+    /// Size in bytes of a single sample frame as OpenAL stores it, which follows the depth
+    #[inline]
+    pub fn storage_frame_size(&self) -> usize {
+        self.channels.count() * self.bit_depth_as_bytes()
+    }
+
+    /// Number of bytes needed for `frames` sample frames
+    #[inline]
+    pub fn bytes_for(&self, frames: usize) -> usize {
+        frames * self.frame_size()
+    }
+
+    /// Number of whole sample frames in `bytes` bytes
+    #[inline]
+    pub fn frames_for(&self, bytes: usize) -> usize {
+        bytes / self.frame_size()
+    }
+
     pub fn common_stereo32f(sample_rate: ALSampleRate) -> ALFormat {
         ALFormat {
             depth: ALBitDepth::Float32,
@@ -73,11 +278,7 @@ impl ALFormat {
     }
 
     pub fn bit_depth_as_bytes(&self) -> usize {
-        match self.depth {
-            ALBitDepth::Int8 => 1,
-            ALBitDepth::Int16 => 2,
-            ALBitDepth::Float32 => 4,
-        }
+        self.depth.size()
     }
 
     pub fn internal_format(&self) -> ALenum {
@@ -144,4 +345,91 @@ impl ALFormat {
             ALSampleType::UnsignedByte3 => AL_UNSIGNED_BYTE3_SOFT,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Formats as channels, sample type and rate, such as `stereo16@48000` or `51chn32f@44100`
+///
+/// The depth isn't included, so only formats at the natural depth of their sample type round trip through `FromStr`.
+impl Display for ALFormat {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}{}@{}", self.channels.token(), self.sample_type.token(), self.sample_rate)
+    }
+}
+
+impl FromStr for ALFormat {
+    type Err = TraceError<ALError>;
+
+    fn from_str(s: &str) -> ALResult<ALFormat> {
+        let mut parts = s.splitn(2, '@');
+
+        let (layout, rate) = match (parts.next(), parts.next()) {
+            (Some(layout), Some(rate)) => (layout, rate),
+            _ => throw!(ALError::InvalidValue),
+        };
+
+        let channels = match ALL_CHANNELS.iter().find(|channels| layout.starts_with(channels.token())) {
+            Some(channels) => *channels,
+            None => throw!(ALError::InvalidValue),
+        };
+
+        let sample_type = match ALL_SAMPLE_TYPES.iter().find(|sample_type| &layout[channels.token().len()..] == sample_type.token()) {
+            Some(sample_type) => *sample_type,
+            None => throw!(ALError::InvalidValue),
+        };
+
+        let sample_rate = match rate.parse() {
+            Ok(sample_rate) => sample_rate,
+            Err(_) => throw!(ALError::InvalidValue),
+        };
+
+        ALFormat::new(channels, sample_type, sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        let format: ALFormat = "stereo16@48000".parse().unwrap();
+
+        assert_eq!(format.channels, ALChannels::Stereo);
+        assert_eq!(format.sample_type, ALSampleType::Short);
+        assert_eq!(format.depth, ALBitDepth::Int16);
+        assert_eq!(format.sample_rate, 48000);
+
+        assert_eq!(format.to_string(), "stereo16@48000");
+    }
+
+    #[test]
+    fn frames_and_bytes() {
+        let format = ALFormat::new(ALChannels::Stereo, ALSampleType::Short, 48000).unwrap();
+
+        assert_eq!(format.frame_size(), 4);
+        assert_eq!(format.bytes_for(100), 400);
+        assert_eq!(format.frames_for(400), 100);
+        assert_eq!(format.frames_for(403), 100);
+    }
+
+    #[test]
+    fn bad_strings_are_rejected() {
+        for s in &["stereo16", "stereo16@", "stereo@48000", "surround16@48000", "stereo16@fast", "stereo16@0"] {
+            assert!(s.parse::<ALFormat>().is_err(), "{} parsed", s);
+        }
+    }
+
+    #[test]
+    fn only_narrower_depths_are_rejected() {
+        let mut format = ALFormat::new(ALChannels::Mono, ALSampleType::Short, 44100).unwrap();
+
+        format.depth = ALBitDepth::Float32;
+
+        assert!(format.validate().is_ok());
+
+        format.sample_type = ALSampleType::Double;
+        format.depth = ALBitDepth::Int8;
+
+        assert!(format.validate().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! HRTF (Head-Related Transfer Function) support via `ALC_SOFT_HRTF`
+
+use als::all::*;
+
+use super::error::*;
+use super::device::*;
+use super::attributes::*;
+
+/// An HRTF dataset available on a device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HrtfSpec {
+    /// Index used to select this HRTF with `ALC_HRTF_ID_SOFT`
+    pub index: ALint,
+    /// Name of the HRTF dataset
+    pub name: String,
+}
+
+/// Ways of choosing an HRTF dataset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HrtfSelection {
+    /// Select by index into `ALDevice::hrtfs`
+    Index(ALint),
+    /// Select by name, resolved against `ALDevice::hrtfs` when used
+    Name(String),
+}
+
+impl HrtfSelection {
+    /// Resolve the selection to an HRTF index on the given device
+    ///
+    /// Returns `ALError::InvalidValue` if there is no such HRTF.
+    pub fn resolve(&self, device: &ALDevice) -> ALResult<ALint> {
+        let hrtfs = try_rethrow!(device.hrtfs());
+
+        let found = match *self {
+            HrtfSelection::Index(index) => hrtfs.iter().find(|spec| spec.index == index),
+            HrtfSelection::Name(ref name) => hrtfs.iter().find(|spec| spec.name == *name),
+        };
+
+        match found {
+            Some(spec) => Ok(spec.index),
+            None => throw!(ALError::InvalidValue)
+        }
+    }
+}
+
+/// Status of HRTF processing on a device
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HrtfStatus {
+    /// HRTF is disabled
+    Disabled,
+    /// HRTF is enabled
+    Enabled,
+    /// HRTF is disabled because it was denied by the user configuration
+    Denied,
+    /// HRTF is enabled because it is required by the user configuration
+    Required,
+    /// HRTF is enabled because the device reported headphones
+    HeadphonesDetected,
+    /// HRTF is disabled because the output format doesn't support it
+    UnsupportedFormat,
+}
+
+impl HrtfStatus {
+    pub fn from_alcenum(status: ALCenum) -> ALResult<HrtfStatus> {
+        Ok(match status {
+            ALC_HRTF_DISABLED_SOFT => HrtfStatus::Disabled,
+            ALC_HRTF_ENABLED_SOFT => HrtfStatus::Enabled,
+            ALC_HRTF_DENIED_SOFT => HrtfStatus::Denied,
+            ALC_HRTF_REQUIRED_SOFT => HrtfStatus::Required,
+            ALC_HRTF_HEADPHONES_DETECTED_SOFT => HrtfStatus::HeadphonesDetected,
+            ALC_HRTF_UNSUPPORTED_FORMAT_SOFT => HrtfStatus::UnsupportedFormat,
+            _ => throw!(ALError::InvalidEnum)
+        })
+    }
+
+    /// Returns true if HRTF processing is active
+    pub fn is_enabled(&self) -> bool {
+        match *self {
+            HrtfStatus::Enabled | HrtfStatus::Required | HrtfStatus::HeadphonesDetected => true,
+            _ => false,
+        }
+    }
+}
+
+impl ALDevice {
+    /// List all HRTF datasets available on this device
+    ///
+    /// Returns `ALError::Unsupported` if `ALC_SOFT_HRTF` is not available.
+    pub fn hrtfs(&self) -> ALResult<Vec<HrtfSpec>> {
+        if !self.capabilities().hrtf {
+            throw!(ALError::Unsupported);
+        }
+
+        let count = try_rethrow!(self.get_integer(ALC_NUM_HRTF_SPECIFIERS_SOFT));
+
+        let mut hrtfs = Vec::with_capacity(count as usize);
+
+        for i in 0..count {
+            hrtfs.push(HrtfSpec {
+                index: i,
+                name: try_rethrow!(self.get_stringi(ALC_HRTF_SPECIFIER_SOFT, i)).into_owned(),
+            });
+        }
+
+        Ok(hrtfs)
+    }
+
+    /// Current HRTF status of this device
+    pub fn hrtf_status(&self) -> ALResult<HrtfStatus> {
+        if !self.capabilities().hrtf {
+            throw!(ALError::Unsupported);
+        }
+
+        HrtfStatus::from_alcenum(try_rethrow!(self.get_integer(ALC_HRTF_STATUS_SOFT)))
+    }
+
+    /// Name of the HRTF dataset in use, if HRTF is enabled
+    pub fn hrtf_name(&self) -> ALResult<Option<String>> {
+        if try_rethrow!(self.hrtf_status()).is_enabled() {
+            Ok(Some(try_rethrow!(self.get_string(ALC_HRTF_SPECIFIER_SOFT)).into_owned()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Enable HRTF with the selected dataset, or disable it when given `None`,
+    /// without recreating the device or any of its contexts.
+    pub fn select_hrtf(&self, selection: Option<HrtfSelection>) -> ALResult<HrtfStatus> {
+        let attributes = match selection {
+            Some(selection) => ALContextAttributes::new().hrtf_selection(selection),
+            None => ALContextAttributes::new().hrtf(false),
+        };
+
+        try_rethrow!(self.reset(&attributes));
+
+        self.hrtf_status()
+    }
+}
@@ -33,99 +33,125 @@ impl ALListener {
     pub fn device(&self) -> Arc<ALDevice> { self.context.device() }
 
     pub fn set_distance_model(&self, model: Option<ALDistanceModel>) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         unsafe { alDistanceModel(model.map_or(AL_NONE, |m| m.to_alenum())); }
 
-        check_al_errors!();
+        check_al_errors!(fn alDistanceModel);
 
         Ok(())
     }
 
     #[inline]
     pub fn get_distance_model(&self) -> ALResult<ALDistanceModel> {
+        try_rethrow!(self.context.ensure_current());
+
         ALDistanceModel::from_alenum(try_rethrow!(ALState::get_integer(AL_DISTANCE_MODEL)))
     }
 
     pub fn set_doppler_factor(&self, factor: ALfloat) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         unsafe { alDopplerFactor(factor); }
 
-        check_al_errors!();
+        check_al_errors!(fn alDopplerFactor);
 
         Ok(())
     }
 
     #[inline]
     pub fn get_doppler_factor(&self) -> ALResult<ALfloat> {
+        try_rethrow!(self.context.ensure_current());
+
         ALState::get_float(AL_DOPPLER_FACTOR)
     }
 
     pub fn set_speed_of_sound(&self, value: ALfloat) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         unsafe { alSpeedOfSound(value); }
 
-        check_al_errors!();
+        check_al_errors!(fn alSpeedOfSound);
 
         Ok(())
     }
 
     #[inline]
     pub fn get_speed_of_sound(&self) -> ALResult<ALfloat> {
+        try_rethrow!(self.context.ensure_current());
+
         ALState::get_float(AL_SPEED_OF_SOUND)
     }
 
     pub fn set_gain(&self, gain: ALfloat) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         unsafe { alListenerf(AL_GAIN, gain); }
 
-        check_al_errors!();
+        check_al_errors!(fn alListenerf, param AL_GAIN);
 
         Ok(())
     }
 
     pub fn get_gain(&self) -> ALResult<ALfloat> {
+        try_rethrow!(self.context.ensure_current());
+
         let mut gain = 0.0;
 
         unsafe { alGetListenerf(AL_GAIN, &mut gain); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetListenerf, param AL_GAIN);
 
         Ok(gain)
     }
 
     pub fn set_velocity(&self, velocity: Vector3<f32>) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         unsafe { alListener3f(AL_VELOCITY, velocity.x as ALfloat, velocity.y as ALfloat, velocity.z as ALfloat); }
 
-        check_al_errors!();
+        check_al_errors!(fn alListener3f, param AL_VELOCITY);
 
         Ok(())
     }
 
     pub fn set_position(&self, position: Point3<f32>) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         unsafe { alListener3f(AL_POSITION, position.x as ALfloat, position.y as ALfloat, position.z as ALfloat); }
 
-        check_al_errors!();
+        check_al_errors!(fn alListener3f, param AL_POSITION);
 
         Ok(())
     }
 
     pub fn get_velocity(&self) -> ALResult<Vector3<f32>> {
+        try_rethrow!(self.context.ensure_current());
+
         let mut velocity: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
 
         unsafe { alGetListenerfv(AL_VELOCITY, &mut velocity as *mut _ as *mut ALfloat); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetListenerfv, param AL_VELOCITY);
 
         Ok(velocity)
     }
 
     pub fn get_position(&self) -> ALResult<Point3<f32>> {
+        try_rethrow!(self.context.ensure_current());
+
         let mut position: Point3<f32> = Point3::new(0.0, 0.0, 0.0);
 
         unsafe { alGetListenerfv(AL_POSITION, &mut position as *mut _ as *mut ALfloat); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetListenerfv, param AL_POSITION);
 
         Ok(position)
     }
 
     pub fn set_orientation(&self, at: Vector3<f32>, up: Option<Vector3<f32>>) -> ALResult<()> {
+        try_rethrow!(self.context.ensure_current());
+
         let up = up.unwrap_or(Vector3::new(0.0, 1.0, 0.0));
 
         //Combine them into a single 6-element vector so it can be passed by memory location
@@ -133,18 +159,20 @@ impl ALListener {
 
         unsafe { alListenerfv(AL_ORIENTATION, &at_up as *const _ as *const ALfloat); }
 
-        check_al_errors!();
+        check_al_errors!(fn alListenerfv, param AL_ORIENTATION);
 
         Ok(())
     }
 
     pub fn get_orientation(&self) -> ALResult<(Vector3<f32>, Vector3<f32>)> {
+        try_rethrow!(self.context.ensure_current());
+
         //Same as above
         let mut at_up = Vector6::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
 
         unsafe { alGetListenerfv(AL_ORIENTATION, &mut at_up as *mut _ as *mut ALfloat); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetListenerfv, param AL_ORIENTATION);
 
         //Get the individual halves
         let at = Vector3::new(at_up.x, at_up.y, at_up.z);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 pub use als::types;
 
 #[macro_use]
@@ -10,8 +12,17 @@ pub trait ALObject {
     fn into_raw(self) -> types::ALuint;
     fn is_valid(&self) -> bool;
 
+    /// The context owning this object, or `None` for objects shared by all contexts of a device
+    #[inline(always)]
+    fn context(&self) -> Option<&ALContext> { None }
+
+    /// Check that the owning context is current and the object is valid
     #[inline(always)]
     fn check(&self) -> ALResult<()> {
+        if let Some(context) = self.context() {
+            try_rethrow!(context.ensure_current());
+        }
+
         if self.is_valid() { Ok(()) } else {
             throw!(ALError::InvalidValue);
         }
@@ -19,8 +30,10 @@ pub trait ALObject {
 }
 
 macro_rules! impl_simple_alobject {
-    (simple $name:ident, $is:ident $(, { $extra_cond:expr } )*) => {
+    (simple $name:ident, $is:ident, items { $($extra_item:item)* } $(, { $extra_cond:expr } )*) => {
         impl $crate::al::ALObject for $name {
+            $($extra_item)*
+
             #[inline(always)]
             fn raw(&self) -> ALuint { self.0 }
 
@@ -36,6 +49,18 @@ macro_rules! impl_simple_alobject {
         }
     };
 
+    // Objects owned by a context, reachable through `self.$field`
+    (simple $name:ident, $is:ident, context $field:tt $(, { $extra_cond:expr } )*) => {
+        impl_simple_alobject!(simple $name, $is, items {
+            #[inline(always)]
+            fn context(&self) -> Option<&$crate::al::ALContext> { Some(&**self.$field) }
+        } $(, { $extra_cond } )*);
+    };
+
+    (simple $name:ident, $is:ident $(, { $extra_cond:expr } )*) => {
+        impl_simple_alobject!(simple $name, $is, items {} $(, { $extra_cond } )*);
+    };
+
     (struct $name:ident, $is:ident $(, { $extra_cond:expr } )*) => {
         impl $crate::al::ALObject for $name {
             #[inline(always)]
@@ -56,22 +81,44 @@ macro_rules! impl_simple_alobject {
 
 pub mod device;
 pub mod context;
+pub mod attributes;
+pub mod hrtf;
 pub mod buffer;
 pub mod source;
 pub mod source_3d;
 pub mod listener;
 pub mod state;
 pub mod format;
+pub mod sample;
 pub mod distance_model;
 pub mod effect;
 pub mod effects;
+pub mod capabilities;
 
-pub use self::device::{ALDevice, ALDeviceArc, NULL_DEVICE};
-pub use self::context::{ALContext, ALContextArc};
+pub use self::device::{ALDevice, DeviceInfo, PausedDevice, ALDeviceArc, ALLoopbackDevice, ALCaptureDevice, NULL_DEVICE};
+pub use self::context::{ALContext, ALContextArc, CurrentContextGuard, DeferredUpdates};
+pub use self::attributes::ALContextAttributes;
+pub use self::hrtf::{HrtfSpec, HrtfSelection, HrtfStatus};
 pub use self::buffer::ALBuffer;
 pub use self::source::{ALSource, ALSourceKind, ALSourceState};
 pub use self::source_3d::ALSource3D;
 pub use self::listener::{ALListener, ALListenerArc};
 pub use self::state::ALState;
 pub use self::format::{ALFormat, ALSampleRate, ALBitDepth, ALSampleType, ALChannels};
-pub use self::distance_model::ALDistanceModel;
\ No newline at end of file
+pub use self::sample::{ALSample, ALFrame};
+pub use self::distance_model::ALDistanceModel;
+pub use self::capabilities::ALCapabilities;
+
+// Compile-time checks that shared handles can be used from any thread
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn is_send_sync<T: Send + Sync>() {}
+
+    is_send_sync::<Arc<ALDevice>>();
+    is_send_sync::<Arc<ALContext>>();
+    is_send_sync::<Arc<ALListener>>();
+    is_send_sync::<Arc<ALBuffer>>();
+    is_send_sync::<Arc<ALSource>>();
+    is_send_sync::<Arc<ALSource3D>>();
+    is_send_sync::<Arc<effect::ALEffect>>();
+}
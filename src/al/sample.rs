@@ -0,0 +1,95 @@
+//! Rust types for samples and sample frames of buffer data
+
+use super::format::*;
+
+/// A single sample of one channel
+///
+/// This is unsafe to implement, as buffer data is handed to OpenAL as raw memory
+/// and must match `sample_type` exactly.
+pub unsafe trait ALSample: Copy {
+    fn sample_type() -> ALSampleType;
+}
+
+/// A single sample frame, holding one sample for every channel
+///
+/// Plain samples are mono frames, and arrays of 1, 2, 4, 6, 7 or 8 samples are
+/// mono, stereo, quadraphonic, 5.1, 6.1 and 7.1 frames respectively.
+///
+/// This is unsafe to implement, as buffer data is handed to OpenAL as raw memory
+/// and must consist of exactly `channels().count()` samples.
+pub unsafe trait ALFrame: Copy {
+    type Sample: ALSample;
+
+    fn channels() -> ALChannels;
+
+    /// Format of buffer data made of these frames
+    fn format(sample_rate: ALSampleRate) -> ALFormat {
+        let sample_type = <Self::Sample as ALSample>::sample_type();
+
+        ALFormat {
+            depth: sample_type.depth(),
+            channels: Self::channels(),
+            sample_rate: sample_rate,
+            sample_type: sample_type,
+        }
+    }
+
+    /// Check if buffer data made of these frames can be buffered as `format`
+    ///
+    /// Channel layouts with the same number of channels, such as stereo and rear, are interchangeable.
+    fn matches(format: &ALFormat) -> bool {
+        format.sample_type == <Self::Sample as ALSample>::sample_type() &&
+        format.channels.count() == Self::channels().count()
+    }
+}
+
+macro_rules! impl_sample {
+    ($($ty:ty => $sample_type:ident),*) => {
+        $(
+            unsafe impl ALSample for $ty {
+                #[inline(always)]
+                fn sample_type() -> ALSampleType { ALSampleType::$sample_type }
+            }
+
+            unsafe impl ALFrame for $ty {
+                type Sample = $ty;
+
+                #[inline(always)]
+                fn channels() -> ALChannels { ALChannels::Mono }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_frame {
+    ($($count:expr => $channels:ident),*) => {
+        $(
+            unsafe impl<S: ALSample> ALFrame for [S; $count] {
+                type Sample = S;
+
+                #[inline(always)]
+                fn channels() -> ALChannels { ALChannels::$channels }
+            }
+        )*
+    }
+}
+
+impl_sample! {
+    i8 => Byte,
+    u8 => UnsignedByte,
+    i16 => Short,
+    u16 => UnsignedShort,
+    i32 => Int,
+    u32 => UnsignedInt,
+    f32 => Float,
+    f64 => Double
+}
+
+impl_frame! {
+    1 => Mono,
+    2 => Stereo,
+    4 => Quad,
+    6 => Point51,
+    7 => Point61,
+    8 => Point71
+}
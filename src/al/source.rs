@@ -1,8 +1,7 @@
 use als::all::*;
 
 use std::mem;
-use std::sync::Arc;
-use std::cell::RefCell;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use super::error::*;
 use super::buffer::*;
@@ -10,9 +9,9 @@ use super::listener::*;
 
 use super::ALObject;
 
-pub struct ALSource(ALuint, RefCell<Vec<Arc<ALBuffer>>>, Arc<ALListener>);
+pub struct ALSource(ALuint, Mutex<Vec<Arc<ALBuffer>>>, Arc<ALListener>);
 
-impl_simple_alobject!(simple ALSource, alIsSource);
+impl_simple_alobject!(simple ALSource, alIsSource, context 2);
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ALSourceState {
@@ -36,7 +35,7 @@ macro_rules! impl_simple_func {
 
             unsafe { $al_name(self.0); }
 
-            check_al_errors!();
+            check_al_errors!(fn $al_name, object self.0);
 
             Ok(())
         }
@@ -52,7 +51,7 @@ macro_rules! impl_simple_property {
 
             unsafe { alGetSourcef(self.0, $al_enum, &mut $name); }
 
-            check_al_errors!();
+            check_al_errors!(fn alGetSourcef, object self.0, param $al_enum);
 
             Ok($name as f32)
         }
@@ -62,7 +61,7 @@ macro_rules! impl_simple_property {
 
             unsafe { alSourcef(self.0, $al_enum, $name); }
 
-            check_al_errors!();
+            check_al_errors!(fn alSourcef, object self.0, param $al_enum);
 
             Ok(())
         }
@@ -71,21 +70,33 @@ macro_rules! impl_simple_property {
 
 impl ALSource {
     pub fn new(listener: Arc<ALListener>) -> ALResult<Arc<ALSource>> {
+        try_rethrow!(listener.ensure_current());
+
         let mut source: ALuint = 0;
 
         unsafe { alGenSources(1, &mut source as *mut _); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGenSources);
+
+        Ok(Arc::new(ALSource(source, Mutex::new(Vec::new()), listener)))
+    }
 
-        Ok(Arc::new(ALSource(source, RefCell::new(Vec::new()), listener)))
+    fn queue(&self) -> MutexGuard<Vec<Arc<ALBuffer>>> {
+        // A panic while holding the lock can't leave the buffer list in a broken state
+        match self.1.lock() {
+            Ok(buffers) => buffers,
+            Err(poisoned) => poisoned.into_inner(),
+        }
     }
 
     pub fn kind(&self) -> ALResult<ALSourceKind> {
+        try_rethrow!(self.check());
+
         let mut kind = 0;
 
         unsafe { alGetSourcei(self.0, AL_SOURCE_TYPE, &mut kind); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetSourcei, object self.0, param AL_SOURCE_TYPE);
 
         Ok(match kind {
             AL_UNDETERMINED => ALSourceKind::Undetermined,
@@ -99,12 +110,12 @@ impl ALSource {
     pub fn queue_buffers<I: Iterator<Item = Arc<ALBuffer>>>(&self, buffer_iter: I) -> ALResult<()> {
         try_rethrow!(self.check());
 
-        let mut buffers = self.1.borrow_mut();
+        let mut buffers = self.queue();
 
         for buffer in buffer_iter {
             unsafe { alSourceQueueBuffers(self.0, 1, &buffer.raw() as *const _); }
 
-            check_al_errors!();
+            check_al_errors!(fn alSourceQueueBuffers, object self.0);
 
             buffers.push(buffer);
         }
@@ -115,12 +126,12 @@ impl ALSource {
     pub fn unqueue_buffer(&self, buffer: Arc<ALBuffer>) -> ALResult<bool> {
         try_rethrow!(self.check());
 
-        let mut buffers = self.1.borrow_mut();
+        let mut buffers = self.queue();
 
         Ok(if let Some(position) = buffers.iter().position(|b| *b == buffer) {
             unsafe { alSourceUnqueueBuffers(self.0, 1, &mut buffer.raw() as *mut _); }
 
-            check_al_errors!();
+            check_al_errors!(fn alSourceUnqueueBuffers, object self.0);
 
             buffers.remove(position);
 
@@ -132,12 +143,12 @@ impl ALSource {
     pub fn unqueue_all_buffers(&self) -> ALResult<Vec<Arc<ALBuffer>>> {
         try_rethrow!(self.check());
 
-        let mut buffers = self.1.borrow_mut();
+        let mut buffers = self.queue();
 
         for buffer in buffers.iter() {
             unsafe { alSourceUnqueueBuffers(self.0, 1, &mut buffer.raw() as *mut _); }
 
-            check_al_errors!();
+            check_al_errors!(fn alSourceUnqueueBuffers, object self.0);
         }
 
         let mut new = Vec::new();
@@ -149,7 +160,7 @@ impl ALSource {
 
     /// Get all buffers that are actively queued.
     pub fn buffers(&self) -> Vec<Arc<ALBuffer>> {
-        self.1.borrow().clone()
+        self.queue().clone()
     }
 
     /// Returns the number of buffers queued to OpenAL.
@@ -163,7 +174,7 @@ impl ALSource {
 
         unsafe { alGetSourcei(self.0, AL_BUFFERS_QUEUED, &mut count); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetSourcei, object self.0, param AL_BUFFERS_QUEUED);
 
         Ok(count as usize)
     }
@@ -176,7 +187,7 @@ impl ALSource {
 
         unsafe { alGetSourcei(self.0, AL_BUFFERS_PROCESSED, &mut count); }
 
-        check_al_errors!();
+        check_al_errors!(fn alGetSourcei, object self.0, param AL_BUFFERS_PROCESSED);
 
         Ok(count as usize)
     }
@@ -186,7 +197,7 @@ impl ALSource {
 
         unsafe { alSourcei(self.0, AL_LOOPING, if looping { AL_TRUE } else { AL_FALSE } as ALint); }
 
-        check_al_errors!();
+        check_al_errors!(fn alSourcei, object self.0, param AL_LOOPING);
 
         Ok(())
     }
@@ -224,8 +235,16 @@ impl ALSource {
 
 impl Drop for ALSource {
     fn drop(&mut self) {
-        unsafe { alDeleteSources(1, &self.0) }
+        // Deleting by name in another context could destroy an unrelated source,
+        // so without thread-local contexts the owning context must already be current
+        let bound = self.2.bind();
 
-        ALError::check().unwrap();
+        if bound.is_ok() || self.2.ensure_current().is_ok() {
+            unsafe { alDeleteSources(1, &self.0) }
+
+            ALError::report_drop_error(ALError::check());
+        } else if let Err(err) = bound {
+            ALError::report_drop_error(Err(err));
+        }
     }
 }
\ No newline at end of file
@@ -24,7 +24,7 @@ macro_rules! impl_property {
 
             unsafe { alGetSourcefv(self.raw(), $al_enum, &mut $name as *mut _ as *mut $alt); }
 
-            check_al_errors!();
+            check_al_errors!(fn alGetSourcefv, object self.raw(), param $al_enum);
 
             Ok($name)
         }
@@ -34,7 +34,7 @@ macro_rules! impl_property {
 
             unsafe { alSourcefv(self.raw(), $al_enum, &$name as *const _ as *const $alt); }
 
-            check_al_errors!();
+            check_al_errors!(fn alSourcefv, object self.raw(), param $al_enum);
 
             Ok(())
         }
@@ -52,9 +52,11 @@ impl ALSource3D {
     pub fn from_source(source: Arc<ALSource>) -> ALResult<Arc<ALSource3D>> {
         let source = ALSource3D(source);
 
+        try_rethrow!(source.check());
+
         unsafe { alSourcei(source.raw(), AL_SOURCE_RELATIVE, AL_TRUE as ALint); }
 
-        check_al_errors!();
+        check_al_errors!(fn alSourcei, object source.raw(), param AL_SOURCE_RELATIVE);
 
         Ok(Arc::new(source))
     }
@@ -62,9 +64,11 @@ impl ALSource3D {
     /// Convert an `ALSource3D` back into an `ALSource`,
     /// simultaneously disabling 3D relative positioning of the source.
     pub fn into_source(self) -> ALResult<Arc<ALSource>> {
+        try_rethrow!(self.check());
+
         unsafe { alSourcei(self.raw(), AL_SOURCE_RELATIVE, AL_FALSE as ALint); }
 
-        check_al_errors!();
+        check_al_errors!(fn alSourcei, object self.raw(), param AL_SOURCE_RELATIVE);
 
         Ok(self.0)
     }
@@ -75,13 +79,19 @@ impl ALSource3D {
 
     /// Set the distance model of this particular source
     ///
+    /// Requires `AL_EXT_source_distance_model`.
+    ///
     /// **NOTE**: `alEnable(AL_SOURCE_DISTANCE_MODEL)` must be called before this.
     pub fn set_distance_model(&self, model: Option<ALDistanceModel>) -> ALResult<()> {
         try_rethrow!(self.check());
 
+        if !self.context().map_or(false, |context| context.capabilities().source_distance_model) {
+            throw!(ALError::Unsupported);
+        }
+
         unsafe { alSourcei(self.raw(), AL_DISTANCE_MODEL, model.map_or(AL_NONE, |m| m.to_alenum())); }
 
-        check_al_errors!();
+        check_al_errors!(fn alSourcei, object self.raw(), param AL_DISTANCE_MODEL);
 
         Ok(())
     }
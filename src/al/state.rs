@@ -12,7 +12,7 @@ impl ALState {
     pub fn enable(param: ALenum) -> ALResult<()> {
         unsafe { alEnable(param); }
 
-        check_al_errors!();
+        check_al_errors!(fn alEnable, param param);
 
         Ok(())
     }
@@ -20,7 +20,7 @@ impl ALState {
     pub fn disable(param: ALenum) -> ALResult<()> {
         unsafe { alDisable(param); }
 
-        check_al_errors!();
+        check_al_errors!(fn alDisable, param param);
 
         Ok(())
     }
@@ -28,7 +28,18 @@ impl ALState {
     pub fn is_enabled(param: ALenum) -> ALResult<bool> {
         let res = unsafe { alIsEnabled(param) };
 
-        check_al_errors!();
+        check_al_errors!(fn alIsEnabled, param param);
+
+        Ok(res == AL_TRUE)
+    }
+
+    /// Check if an AL extension is supported by the current context
+    pub fn extension_present(extension: &str) -> ALResult<bool> {
+        let c_ext = try_throw!(CString::new(extension));
+
+        let res = unsafe { alIsExtensionPresent(c_ext.as_ptr() as *const _) };
+
+        check_al_errors!(fn alIsExtensionPresent);
 
         Ok(res == AL_TRUE)
     }
@@ -36,7 +47,7 @@ impl ALState {
     pub fn get_string<'a>(param: ALenum) -> ALResult<Cow<'a, str>> {
         let c_str = unsafe { alGetString(param) };
 
-        check_al_errors!();
+        check_al_errors!(fn alGetString, param param);
 
         Ok(unsafe { CStr::from_ptr(c_str).to_string_lossy() })
     }
@@ -44,7 +55,7 @@ impl ALState {
     pub fn get_integer(param: ALenum) -> ALResult<ALint> {
         let res = unsafe { alGetInteger(param) };
 
-        check_al_errors!();
+        check_al_errors!(fn alGetInteger, param param);
 
         Ok(res)
     }
@@ -52,7 +63,7 @@ impl ALState {
     pub fn get_float(param: ALenum) -> ALResult<ALfloat> {
         let res = unsafe { alGetFloat(param) };
 
-        check_al_errors!();
+        check_al_errors!(fn alGetFloat, param param);
 
         Ok(res)
     }
@@ -60,7 +71,7 @@ impl ALState {
     pub fn get_double(param: ALenum) -> ALResult<ALdouble> {
         let res = unsafe { alGetDouble(param) };
 
-        check_al_errors!();
+        check_al_errors!(fn alGetDouble, param param);
 
         Ok(res)
     }
@@ -70,7 +81,7 @@ impl ALState {
 
         let res = unsafe { alGetEnumValue(c_str.as_ptr()) };
 
-        check_al_errors!();
+        check_al_errors!(fn alGetEnumValue);
 
         if res == 0 || res == -1 {
             throw!(ALError::InvalidEnum)
@@ -15,33 +15,14 @@ fn main() {
 }
 
 fn run() -> ALResult<()> {
-    let enumerate_all = try_rethrow!(NULL_DEVICE.extension_present(ALC_ENUMERATE_ALL_EXT_NAME));
-
-    let playback_devices = if enumerate_all {
-        try_rethrow!(NULL_DEVICE.get_multistring(ALC_ALL_DEVICES_SPECIFIER))
-    } else {
-        try_rethrow!(NULL_DEVICE.get_multistring(ALC_DEVICE_SPECIFIER))
-    };
-
     println!("Available playback devices:");
-    for device in &playback_devices {
-        println!("  {}", device);
+    for device in &try_rethrow!(ALDevice::enumerate_playback()) {
+        println!("  {}{}", device.name, if device.is_default { " (default)" } else { "" });
     }
 
     println!("Available capture devices:");
-    for device in &try_rethrow!(NULL_DEVICE.get_multistring(ALC_CAPTURE_DEVICE_SPECIFIER)) {
-        println!("  {}", device);
-    }
-
-    let default_devices = if enumerate_all {
-        try_rethrow!(NULL_DEVICE.get_multistring(ALC_DEFAULT_ALL_DEVICES_SPECIFIER))
-    } else {
-        try_rethrow!(NULL_DEVICE.get_multistring(ALC_DEFAULT_DEVICE_SPECIFIER))
-    };
-
-    println!("Default playback devices:");
-    for device in &default_devices {
-        println!("  {}", device);
+    for device in &try_rethrow!(ALDevice::enumerate_capture()) {
+        println!("  {}{}", device.name, if device.is_default { " (default)" } else { "" });
     }
 
     try_rethrow!(alc_info(NULL_DEVICE.clone()));
@@ -82,16 +63,18 @@ fn alc_info(device: Arc<ALDevice>) -> ALResult<()> {
 }
 
 fn hrtf_info(device: Arc<ALDevice>) -> ALResult<()> {
-    if try_rethrow!(device.extension_present(ALC_SOFT_HRTF_NAME)) {
-        let num_hrtfs = try_rethrow!(device.get_integer(ALC_NUM_HRTF_SPECIFIERS_SOFT));
+    if device.capabilities().hrtf {
+        let hrtfs = try_rethrow!(device.hrtfs());
 
-        if num_hrtfs == 0 {
+        if hrtfs.is_empty() {
             println!("No HRTFs found");
         } else {
-            for i in 0..num_hrtfs {
-                println!("  {}", try_rethrow!(device.get_stringi(ALC_HRTF_SPECIFIER_SOFT, i)));
+            for hrtf in &hrtfs {
+                println!("  {}", hrtf.name);
             }
         }
+
+        println!("HRTF status: {:?}", try_rethrow!(device.hrtf_status()));
     } else {
         println!("HRTF extension not available");
     }
@@ -113,7 +96,7 @@ fn al_info() -> ALResult<()> {
 }
 
 fn efx_info(device: Arc<ALDevice>) -> ALResult<()> {
-    if try_rethrow!(device.extension_present(ALC_EXT_EFX_NAME)) {
+    if device.capabilities().efx {
         let major = try_rethrow!(device.get_integer(ALC_EFX_MAJOR_VERSION));
         let minor = try_rethrow!(device.get_integer(ALC_EFX_MINOR_VERSION));
 